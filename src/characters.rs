@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+
+/// Radical, strokes and decomposition of a character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharInfo {
+    /// The radical as written in the character, like 氵 rather than 水.
+    pub radical: char,
+    /// Number of the radical among the 214 Kangxi radicals.
+    pub radical_number: u8,
+    pub strokes: u8,
+    /// Ideographic Description Sequence, like ⿰女子.
+    pub ids: &'static str,
+}
+
+static DATA: Lazy<HashMap<char, CharInfo>> = Lazy::new(|| {
+    include_str!("../assets/characters.txt")
+        .lines()
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let mut fields = l.split('\t');
+            let c = fields.next()?.chars().next()?;
+            let info = CharInfo {
+                radical: fields.next()?.chars().next()?,
+                radical_number: fields.next()?.parse().ok()?,
+                strokes: fields.next()?.parse().ok()?,
+                ids: fields.next()?,
+            };
+            Some((c, info))
+        })
+        .collect()
+});
+
+pub fn info(c: char) -> Option<&'static CharInfo> {
+    DATA.get(&c)
+}
+
+/// The components of a character, if it splits into any.
+pub fn components(c: char) -> Vec<char> {
+    info(c)
+        .map(|info| {
+            info.ids
+                .chars()
+                // Skip the ⿰ style description characters.
+                .filter(|comp| !('\u{2ff0}'..='\u{2fff}').contains(comp) && *comp != c)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A line like "好: radical 女 (38), 6 strokes, ⿰女子".
+pub fn describe(c: char) -> Option<String> {
+    let info = info(c)?;
+    let mut result = format!(
+        "{c}: radical {} ({}), {} strokes",
+        info.radical, info.radical_number, info.strokes
+    );
+    if !components(c).is_empty() {
+        result.push_str(", ");
+        result.push_str(info.ids);
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_characters() {
+        let info = info('漢').unwrap();
+        assert_eq!(info.radical, '氵');
+        assert_eq!(info.radical_number, 85);
+        assert_eq!(info.strokes, 14);
+        assert_eq!(components('好'), vec!['女', '子']);
+        assert!(components('人').is_empty());
+        assert_eq!(
+            describe('好').unwrap(),
+            "好: radical 女 (38), 6 strokes, ⿰女子"
+        );
+        assert_eq!(describe('人').unwrap(), "人: radical 人 (9), 2 strokes");
+        assert_eq!(describe('a'), None);
+        // Every line of the data file parses.
+        let lines = include_str!("../assets/characters.txt")
+            .lines()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .count();
+        assert_eq!(DATA.len(), lines);
+    }
+}
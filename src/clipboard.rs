@@ -1,13 +1,17 @@
+use std::iter;
+
 use dioxus::prelude::*;
 use futures::prelude::*;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::Clipboard;
 
 use crate::{
-    config::{Config, Hint},
+    config::{Config, Hint, HintPosition},
+    convert::convert,
     input::InputAction,
+    known::{Familiarity, KnownWords},
     phonetic as ph,
-    words::Segment,
+    words::{word_hint, Segment},
 };
 
 pub fn get() -> Option<Clipboard> {
@@ -65,56 +69,261 @@ pub async fn copy_service(
     mut rx: UnboundedReceiver<CopyAction>,
     cfg: UseRef<Config>,
     segments: UseRef<Vec<Segment>>,
+    known: UseRef<KnownWords>,
 ) {
-    let mut result = String::with_capacity(1024);
     while let Some(msg) = rx.next().await {
         let currcfg = cfg.read().to_owned();
-        let is_chars = matches!(msg, CopyAction::Characters);
-        let is_simp = currcfg.simplified;
-        let hint_typ = currcfg.hint;
-
-        result.clear();
         let segs = segments.read().to_owned();
+        let result = match msg {
+            CopyAction::Characters => characters(&segs, currcfg.simplified),
+            CopyAction::Annotations => annotations(&segs, &currcfg, &known.read()),
+        };
+        self::copy(result.trim_end()).await;
+    }
+}
 
-        for seg in segs.iter() {
-            match seg {
-                Segment::Chinese(words) => {
-                    if words.is_empty() {
-                        continue;
-                    }
-                    let we = words[0];
-                    if is_chars {
-                        result.push_str(if is_simp {
-                            &we.simplified
-                        } else {
-                            &we.traditional
-                        });
+fn characters(segs: &[Segment], is_simp: bool) -> String {
+    let mut result = String::with_capacity(1024);
+    for seg in segs {
+        match seg {
+            Segment::Chinese(word) => {
+                if let Some(we) = word.defs.first() {
+                    result.push_str(if is_simp {
+                        &we.simplified
                     } else {
-                        we.pinyin_numbers
-                            .split_whitespace()
-                            .zip(we.tone_marks.clone())
-                            .zip(we.pinyin_marks.split_whitespace())
-                            .for_each(|((pinyin, tone), pinyintm)| {
-                                let phon =
-                                    ph::Syllable::from_pinyin(pinyin).unwrap_or(ph::Syllable {
-                                        init: ph::Initial::Q,
-                                        fin: ph::Final::A,
-                                    });
-                                if let Some(hint) = crate::words::generate_hint(
-                                    hint_typ, &phon, tone, we.hsk, pinyintm,
-                                ) {
-                                    result.push_str(&hint);
-                                    result.push(' ');
-                                }
+                        &we.traditional
+                    });
+                }
+            }
+            Segment::Number(num) => result.push_str(&convert(&num.text, is_simp)),
+            Segment::UnknownHan(txt) => result.push_str(&convert(txt, is_simp)),
+            Segment::Punctuation(txt)
+            | Segment::Latin(txt)
+            | Segment::Whitespace(txt)
+            | Segment::Plain(txt) => result.push_str(txt),
+            Segment::Break => result.push('\n'),
+        }
+    }
+    result
+}
+
+/// A line of copied annotations: a hint layer or the characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Hint(Hint),
+    Characters,
+}
+
+/// Text that lines up across the rows, like a character and its hints.
+struct Column {
+    cells: Vec<String>,
+    /// Followed by a space, like hints are.
+    spaced: bool,
+}
+
+/// Width of text in a monospace font, where Han characters, zhuyin and
+/// full width punctuation take two columns.
+fn display_width(s: &str) -> usize {
+    s.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115f | 0x2e80..=0xa4cf | 0xac00..=0xd7a3 | 0xf900..=0xfaff => 2,
+            0xfe30..=0xfe4f | 0xff00..=0xff60 | 0xffe0..=0xffe6 | 0x20000..=0x3fffd => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Annotations in the hint layers, separated by spaces. With two layers
+/// each line of text becomes aligned rows of the layers around the
+/// characters.
+fn annotations(segs: &[Segment], currcfg: &Config, known: &KnownWords) -> String {
+    let is_simp = currcfg.simplified;
+    let layers = currcfg.hint_layers();
+    let aligned = layers.len() > 1;
+    let rows = if aligned {
+        // Layers over the characters go above them.
+        let (over, under): (Vec<_>, Vec<_>) = layers
+            .into_iter()
+            .partition(|(_, pos)| *pos == HintPosition::Over);
+        over.into_iter()
+            .rev()
+            .map(|(hint, _)| Row::Hint(hint))
+            .chain(iter::once(Row::Characters))
+            .chain(under.into_iter().map(|(hint, _)| Row::Hint(hint)))
+            .collect::<Vec<_>>()
+    } else {
+        layers
+            .into_iter()
+            .map(|(hint, _)| Row::Hint(hint))
+            .collect()
+    };
+    // Text that isn't annotated only goes with the characters.
+    let text_cell = |txt: &str| Column {
+        cells: rows
+            .iter()
+            .map(|row| match row {
+                Row::Hint(_) if aligned => String::new(),
+                _ => txt.to_owned(),
+            })
+            .collect(),
+        spaced: false,
+    };
+
+    let mut lines = vec![Vec::<Column>::new()];
+    for seg in segs {
+        let line = lines.last_mut().expect("Internal error: No lines");
+        match seg {
+            Segment::Chinese(word) => {
+                let we = match word.defs.first() {
+                    Some(we) => *we,
+                    None => continue,
+                };
+                let chars = if is_simp {
+                    &we.simplified
+                } else {
+                    &we.traditional
+                };
+                let hinted = currcfg.hints_word(we)
+                    && !(currcfg.knownwords && known.get(&we.simplified) == Familiarity::Known);
+                // Words without hints are copied as characters.
+                if !hinted {
+                    line.push(Column {
+                        cells: rows
+                            .iter()
+                            .map(|row| match row {
+                                Row::Hint(_) if aligned => String::new(),
+                                _ => chars.to_owned(),
                             })
-                    }
+                            .collect(),
+                        spaced: true,
+                    });
+                    continue;
+                }
+                let level = currcfg.levels.short_label(currcfg.levels.level(we));
+                let simplified = currcfg.simplified;
+                let columns = we
+                    .pinyin_numbers
+                    .split_whitespace()
+                    .zip(we.tone_marks.clone())
+                    .zip(we.pinyin_marks.split_whitespace())
+                    .zip(chars.chars())
+                    .enumerate()
+                    .map(|(idx, (((pinyin, tone), pinyintm), c))| {
+                        let phon = ph::Syllable::from_pinyin(pinyin).unwrap_or(ph::Syllable {
+                            init: ph::Initial::Q,
+                            fin: ph::Final::A,
+                        });
+                        let cells = rows.iter().map(|row| match row {
+                            Row::Characters => c.to_string(),
+                            // Hints about the word are given once.
+                            Row::Hint(hint) if hint.word_scoped() => (idx == 0)
+                                .then(|| {
+                                    word_hint(*hint, word, &level, simplified, currcfg.glosslen)
+                                })
+                                .flatten()
+                                .unwrap_or_default(),
+                            Row::Hint(hint) => {
+                                crate::words::generate_hint(*hint, c, &phon, tone, pinyintm)
+                                    .map(|h| h.to_string())
+                                    .unwrap_or_default()
+                            }
+                        });
+                        Column {
+                            cells: cells.collect(),
+                            spaced: true,
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let word_scoped = rows
+                    .iter()
+                    .any(|row| matches!(row, Row::Hint(hint) if hint.word_scoped()));
+                if aligned && word_scoped {
+                    // Hints about the word span all of it, so it's one column.
+                    let cells = (0..rows.len())
+                        .map(|row| {
+                            let cells = columns.iter().map(|col| col.cells[row].as_str());
+                            match rows[row] {
+                                Row::Characters => cells.collect::<String>(),
+                                Row::Hint(_) => cells
+                                    .filter(|cell| !cell.is_empty())
+                                    .collect::<Vec<_>>()
+                                    .join(" "),
+                            }
+                        })
+                        .collect();
+                    line.push(Column {
+                        cells,
+                        spaced: true,
+                    });
+                } else {
+                    line.extend(columns);
+                }
+            }
+            Segment::Whitespace(txt) => {
+                // Annotations are already separated by a space.
+                if !matches!(line.last(), Some(col) if col.spaced) {
+                    line.push(text_cell(txt));
                 }
-                Segment::Plain(txt) => result.push_str(txt.as_str()),
-                Segment::Break => result.push('\n'),
             }
+            Segment::Number(num) => num.readings.iter().for_each(|r| {
+                let phon = ph::Syllable::from_pinyin(r.pinyin).unwrap_or(ph::Syllable {
+                    init: ph::Initial::Q,
+                    fin: ph::Final::A,
+                });
+                let cells = rows.iter().map(|row| match row {
+                    Row::Characters => convert(&r.hanzi.to_string(), is_simp),
+                    Row::Hint(hint) => {
+                        crate::words::generate_hint(*hint, r.hanzi, &phon, r.tone, r.marks)
+                            .map(|h| h.to_string())
+                            .unwrap_or_default()
+                    }
+                });
+                line.push(Column {
+                    cells: cells.collect(),
+                    spaced: true,
+                });
+            }),
+            Segment::Punctuation(txt)
+            | Segment::Latin(txt)
+            | Segment::UnknownHan(txt)
+            | Segment::Plain(txt) => line.push(text_cell(txt)),
+            Segment::Break => lines.push(Vec::new()),
         }
-        self::copy(result.trim_end()).await;
     }
+
+    let render_line = |line: &Vec<Column>| {
+        // Columns without anything in them, like the rest of a word after
+        // its word class, are left out.
+        let line = line
+            .iter()
+            .filter(|col| col.cells.iter().any(|cell| !cell.is_empty()))
+            .collect::<Vec<_>>();
+        (0..rows.len())
+            .map(|row| {
+                let mut result = String::new();
+                for col in line.iter() {
+                    let cell = &col.cells[row];
+                    result.push_str(cell);
+                    if aligned {
+                        let width = col.cells.iter().map(|c| display_width(c)).max();
+                        let padding = width.unwrap_or(0) - display_width(cell);
+                        result.extend(iter::repeat(' ').take(padding));
+                    }
+                    if col.spaced {
+                        result.push(' ');
+                    }
+                }
+                result.trim_end().to_owned()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let separator = if aligned { "\n\n" } else { "\n" };
+    lines
+        .iter()
+        .map(render_line)
+        .collect::<Vec<_>>()
+        .join(separator)
 }
 
 #[inline_props]
@@ -130,8 +339,13 @@ pub fn ClipboardFunctions(cx: Scope, cfg: UseRef<Config>) -> Element {
             onclick: move |_| copy_task.send(CopyAction::Characters),
             "子"
         }
-        (cfg.hint != Hint::Off).then(|| {
-            let anntyp = Hint::OPTIONS[cfg.hint as usize];
+        (!cfg.hint_layers().is_empty()).then(|| {
+            let anntyp = cfg
+                .hint_layers()
+                .iter()
+                .map(|(hint, _)| Hint::OPTIONS[*hint as usize])
+                .collect::<Vec<_>>()
+                .join("+");
             rsx! {
                 " | "
                 button {
@@ -157,3 +371,32 @@ pub fn ClipboardFunctions(cx: Scope, cfg: UseRef<Config>) -> Element {
         "]"
     })
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{levels::LevelSystem, words::make_words};
+
+    #[test]
+    pub fn test_annotations() {
+        let (segs, _) = make_words("你好。\n学习 OK", false, LevelSystem::Hsk2);
+        let mut cfg = Config::default();
+        let known = KnownWords::default();
+        assert_eq!(annotations(&segs, &cfg, &known), "ni hao 。\nxue xi OK");
+        cfg.hint2 = Hint::Zhuyin;
+        assert_eq!(
+            annotations(&segs, &cfg, &known),
+            "ㄋㄧ ㄏㄠ\n你   好   。\nni   hao\n\nㄒㄩㄝ ㄒㄧ\n学     习   OK\nxue    xi"
+        );
+        cfg.hint = Hint::Pos;
+        cfg.hint2 = Hint::Off;
+        assert_eq!(annotations(&segs, &cfg, &known), "n. 。\nv. OK");
+        cfg.hint = Hint::Pinyin;
+        cfg.hint2 = Hint::Gloss;
+        cfg.hint2pos = HintPosition::Under;
+        assert_eq!(
+            annotations(&segs, &cfg, &known),
+            "你好   。\nni hao\nhello\n\n学习     OK\nxue xi\nto learn"
+        );
+    }
+}
@@ -1,5 +1,8 @@
+use chinese_dictionary as cd;
 use dioxus::{events::FormEvent, prelude::*};
 
+use crate::levels::LevelSystem;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Hint {
     Off,
@@ -12,6 +15,15 @@ pub enum Hint {
     ToneMark,
     Hsk,
     PinyinTM,
+    Pos,
+    Radical,
+    Strokes,
+    /// English meaning of the whole word, see `words::interlinear_gloss`.
+    Gloss,
+    /// The word in the script that isn't shown, if it's written differently.
+    OtherScript,
+    /// Rank of the word among the most frequent words, see `frequency`.
+    Frequency,
 }
 
 impl Hint {
@@ -26,7 +38,22 @@ impl Hint {
         "tone",
         "HSK",
         "pinyin tm",
+        "word class",
+        "radical",
+        "strokes",
+        "gloss",
+        "other script",
+        "frequency",
     ];
+
+    /// Whether the hint is about the whole word rather than each syllable,
+    /// see `words::word_hint`.
+    pub fn word_scoped(self) -> bool {
+        matches!(
+            self,
+            Self::Hsk | Self::Pos | Self::Gloss | Self::OtherScript | Self::Frequency
+        )
+    }
 }
 
 impl From<usize> for Hint {
@@ -41,30 +68,177 @@ impl From<usize> for Hint {
             7 => Self::ToneMark,
             8 => Self::Hsk,
             9 => Self::PinyinTM,
+            10 => Self::Pos,
+            11 => Self::Radical,
+            12 => Self::Strokes,
+            13 => Self::Gloss,
+            14 => Self::OtherScript,
+            15 => Self::Frequency,
             _ => Self::Off,
         }
     }
 }
 
+/// Where a layer of hints goes relative to the characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HintPosition {
+    Over,
+    Under,
+}
+
+impl HintPosition {
+    pub const OPTIONS: &'static [&'static str] = &["over", "under"];
+
+    pub fn class(self) -> &'static str {
+        match self {
+            Self::Over => "hint-over",
+            Self::Under => "hint-under",
+        }
+    }
+}
+
+impl From<usize> for HintPosition {
+    fn from(val: usize) -> Self {
+        match val {
+            0 => Self::Over,
+            _ => Self::Under,
+        }
+    }
+}
+
+/// Names of the external dictionary link presets, with a last entry for a
+/// custom template.
+pub const LINK_NAMES: &[&str] = &[
+    "MDBG",
+    "Wiktionary",
+    "YellowBridge",
+    "Pleco",
+    "zdic",
+    "custom",
+];
+
+/// URL templates of the link presets. `{simplified}`, `{traditional}` and
+/// `{pinyin}` are replaced with the word.
+pub const LINK_TEMPLATES: &[&str] = &[
+    "https://www.mdbg.net/chinese/dictionary?page=worddict&wdrst=0&wdqb={simplified}",
+    "https://en.wiktionary.org/wiki/{traditional}",
+    "https://www.yellowbridge.com/chinese/dictionary.php?word={simplified}",
+    "plecoapi://x-callback-url/s?q={simplified}",
+    "https://www.zdic.net/hans/{simplified}",
+];
+
+/// Fills in a link template for a word, percent-encoding the values.
+/// `pinyin` is numbered, like in the dictionary.
+pub fn link_url(template: &str, simplified: &str, traditional: &str, pinyin: &str) -> String {
+    template
+        .replace("{simplified}", &percent_encode(simplified))
+        .replace("{traditional}", &percent_encode(traditional))
+        .replace("{pinyin}", &percent_encode(pinyin))
+}
+
+/// Encodes everything but unreserved URL characters as UTF-8 bytes.
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                char::from(b).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Config {
     pub hint: Hint,
+    pub hintpos: HintPosition,
+    /// A second layer of hints, shown around the first. There are only two
+    /// layers, one for each side of the characters that ruby can put them.
+    pub hint2: Hint,
+    pub hint2pos: HintPosition,
     pub tonecolor: bool,
     pub hsk: bool,
     pub simplified: bool,
+    pub autoscript: bool,
     pub wordspace: bool,
     pub tooltips: bool,
+    pub grammar: bool,
+    pub particles: bool,
+    pub poscolor: bool,
+    pub measurewords: bool,
+    /// External dictionary URL template, see `LINK_TEMPLATES`.
+    pub linktemplate: String,
+    /// Link each character separately rather than the whole word.
+    pub linkchars: bool,
+    pub levels: LevelSystem,
+    /// Most characters of a gloss hint, 0 for no limit.
+    pub glosslen: usize,
+    /// Only words above this level get hints, 0 for all words.
+    pub hintlevel: u8,
+    /// Hide hints of known words and highlight the rest, see `known`.
+    pub knownwords: bool,
+    /// Hints are hidden until a word is hovered, clicked or focused.
+    pub revealhints: bool,
+    /// Clicking a word marks it as known instead of showing its details.
+    pub markknown: bool,
+}
+
+impl Config {
+    /// Index into `LINK_NAMES` of the link template in use, the last one
+    /// for a custom or empty template.
+    pub fn link_preset(&self) -> usize {
+        LINK_TEMPLATES
+            .iter()
+            .position(|t| *t == self.linktemplate)
+            .unwrap_or(LINK_TEMPLATES.len())
+    }
+
+    /// The hint layers that are on, inner one first, at most `hint` and
+    /// `hint2`.
+    pub fn hint_layers(&self) -> Vec<(Hint, HintPosition)> {
+        [(self.hint, self.hintpos), (self.hint2, self.hint2pos)]
+            .into_iter()
+            .filter(|(hint, _)| *hint != Hint::Off)
+            .collect()
+    }
+
+    pub fn shows_hint(&self, hint: Hint) -> bool {
+        self.hint == hint || self.hint2 == hint
+    }
+
+    /// Whether a word is above the hint level. Words without a level always
+    /// get hints.
+    pub fn hints_word(&self, we: &cd::WordEntry) -> bool {
+        let level = self.levels.level(we);
+        self.hintlevel == 0 || level == 0 || level > self.hintlevel
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             hint: Hint::Pinyin,
+            hintpos: HintPosition::Under,
+            hint2: Hint::Off,
+            hint2pos: HintPosition::Over,
             tonecolor: true,
             simplified: true,
+            autoscript: false,
             hsk: true,
             wordspace: true,
             tooltips: true,
+            grammar: false,
+            particles: false,
+            poscolor: false,
+            measurewords: false,
+            linktemplate: LINK_TEMPLATES[0].to_owned(),
+            linkchars: false,
+            levels: LevelSystem::Hsk2,
+            glosslen: 20,
+            hintlevel: 0,
+            revealhints: false,
+            knownwords: false,
+            markknown: false,
         }
     }
 }
@@ -90,6 +264,27 @@ pub fn BooleanOption<'a>(cx: Scope<'a, BooleanOptionProps<'a>>) -> Element<'a> {
     })
 }
 
+#[derive(Props)]
+pub struct TextOptionProps<'a> {
+    label: &'a str,
+    current: String,
+    oninput: EventHandler<'a, FormEvent>,
+}
+
+pub fn TextOption<'a>(cx: Scope<'a, TextOptionProps<'a>>) -> Element<'a> {
+    cx.render(rsx! {
+      label {
+        "{cx.props.label}: "
+        input {
+          r#type: "text",
+          size: "40",
+          value: "{cx.props.current}",
+          oninput: move |evt| cx.props.oninput.call(evt)
+        }
+      }
+    })
+}
+
 #[derive(Props)]
 pub struct MultiOptionProps<'a> {
     label: &'a str,
@@ -121,3 +316,24 @@ pub fn MultiOption<'a>(cx: Scope<'a, MultiOptionProps<'a>>) -> Element {
 
     })
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_link_url() {
+        assert_eq!(
+            link_url(LINK_TEMPLATES[1], "学习", "學習", "xue2 xi2"),
+            "https://en.wiktionary.org/wiki/%E5%AD%B8%E7%BF%92"
+        );
+        assert_eq!(
+            link_url("x?q={pinyin}&s={simplified}", "A&B", "A&B", "xue2 xi2"),
+            "x?q=xue2%20xi2&s=A%26B"
+        );
+        let mut cfg = Config::default();
+        assert_eq!(cfg.link_preset(), 0);
+        cfg.linktemplate.clear();
+        assert_eq!(LINK_NAMES[cfg.link_preset()], "custom");
+    }
+}
@@ -0,0 +1,227 @@
+use std::borrow::Cow;
+
+use chinese_dictionary as cd;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{
+    dictionary::dictionary,
+    words::{sort_defs, tokenize},
+};
+
+/// Converts Han characters in `s` to Simplified (`to_simplified`) or
+/// Traditional script, leaving everything else as is.
+///
+/// Words the dictionary knows are converted as a unit, so characters with
+/// several possible forms get the one that fits the word (头发 → 頭髮 but
+/// 发现 → 發現). Anything else is converted one character at a time.
+pub fn convert(s: &str, to_simplified: bool) -> String {
+    static REGEX: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?s)(\p{Han}+)|([^\p{Han}]+)")
+            .expect("Internal error: Could not compile regex")
+    });
+    let mut result = String::with_capacity(s.len());
+
+    for chunk in REGEX.captures_iter(s) {
+        if let Some(han) = chunk.get(1) {
+            tokenize(han.as_str())
+                .into_iter()
+                .for_each(|tok| result.push_str(&convert_word(tok, to_simplified)));
+        } else if let Some(other) = chunk.get(2) {
+            result.push_str(other.as_str());
+        }
+    }
+    result
+}
+
+/// Converts a single word, falling back to converting each character.
+pub fn convert_word(word: &str, to_simplified: bool) -> Cow<'_, str> {
+    if let Some(we) = best_entry(word, to_simplified) {
+        return Cow::from(if to_simplified {
+            we.simplified.as_str()
+        } else {
+            we.traditional.as_str()
+        });
+    }
+    if word.chars().nth(1).is_some() {
+        return Cow::from(
+            word.char_indices()
+                .map(|(idx, c)| convert_word(&word[idx..idx + c.len_utf8()], to_simplified))
+                .collect::<String>(),
+        );
+    }
+    let converted = if to_simplified {
+        cd::traditional_to_simplified(word)
+    } else {
+        cd::simplified_to_traditional(word)
+    };
+    if converted.is_empty() {
+        Cow::from(word)
+    } else {
+        converted
+    }
+}
+
+/// All the forms a character may take in the other script, most likely first.
+pub fn alternatives(c: char, to_simplified: bool) -> Vec<String> {
+    let mut buf = [0u8; 4];
+    let c = c.encode_utf8(&mut buf);
+    let mut defs = lookup_source(c, to_simplified);
+    sort_defs(&mut defs);
+    let mut result = Vec::<String>::new();
+    defs.into_iter()
+        .map(|we| {
+            if to_simplified {
+                &we.simplified
+            } else {
+                &we.traditional
+            }
+        })
+        .for_each(|form| {
+            if !result.contains(form) {
+                result.push(form.to_owned())
+            }
+        });
+    result
+}
+
+fn lookup_source(word: &str, to_simplified: bool) -> Vec<&'static cd::WordEntry> {
+    if to_simplified {
+        dictionary().query_by_traditional(word)
+    } else {
+        dictionary().query_by_simplified(word)
+    }
+}
+
+fn best_entry(word: &str, to_simplified: bool) -> Option<&'static cd::WordEntry> {
+    let mut defs = lookup_source(word, to_simplified);
+    sort_defs(&mut defs);
+    defs.first().copied()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Script {
+    Unknown,
+    Simplified,
+    Traditional,
+    Mixed,
+}
+
+impl Script {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Script::Unknown => "?",
+            Script::Simplified => "简",
+            Script::Traditional => "繁",
+            Script::Mixed => "简/繁",
+        }
+    }
+}
+
+/// Counts of characters that only exist in one of the scripts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScriptCounts {
+    pub simplified: usize,
+    pub traditional: usize,
+}
+
+impl ScriptCounts {
+    /// Share of script specific characters one script needs for the text to
+    /// count as written in it.
+    const THRESHOLD: f32 = 0.8;
+
+    pub fn detect(s: &str) -> Self {
+        s.chars().fold(Self::default(), |mut acc, c| {
+            match char_script(c) {
+                Some(Script::Simplified) => acc.simplified += 1,
+                Some(Script::Traditional) => acc.traditional += 1,
+                _ => (),
+            }
+            acc
+        })
+    }
+
+    pub fn traditional_ratio(&self) -> f32 {
+        let total = self.simplified + self.traditional;
+        if total == 0 {
+            0.0
+        } else {
+            (self.traditional as f32) / (total as f32)
+        }
+    }
+
+    pub fn script(&self) -> Script {
+        let ratio = self.traditional_ratio();
+        if self.simplified + self.traditional == 0 {
+            Script::Unknown
+        } else if ratio >= Self::THRESHOLD {
+            Script::Traditional
+        } else if ratio <= 1.0 - Self::THRESHOLD {
+            Script::Simplified
+        } else {
+            Script::Mixed
+        }
+    }
+}
+
+/// Whether a character is a CJK ideograph.
+pub fn is_han(c: char) -> bool {
+    matches!(c, '\u{3400}'..='\u{9fff}' | '\u{20000}'..='\u{3134f}' | '\u{f900}'..='\u{faff}')
+}
+
+/// Which script a character belongs to, if it only exists in one of them.
+fn char_script(c: char) -> Option<Script> {
+    if !is_han(c) {
+        return None;
+    }
+    let mut buf = [0u8; 4];
+    let c: &str = c.encode_utf8(&mut buf);
+    match (cd::is_simplified(c), cd::is_traditional(c)) {
+        (true, false) => return Some(Script::Simplified),
+        (false, true) => return Some(Script::Traditional),
+        _ => (),
+    }
+    let simp = dictionary().query_by_simplified(c);
+    if !simp.is_empty() && simp.iter().all(|we| we.traditional != c) {
+        return Some(Script::Simplified);
+    }
+    let trad = dictionary().query_by_traditional(c);
+    if !trad.is_empty() && trad.iter().all(|we| we.simplified != c) {
+        return Some(Script::Traditional);
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_convert() {
+        assert_eq!(convert("头发很长, 我发现了", false), "頭髮很長, 我發現了");
+        assert_eq!(convert("頭髮很長, 我發現了", true), "头发很长, 我发现了");
+        assert_eq!(convert("頭髮", false), "頭髮");
+        assert_eq!(convert("㐀好", true), "㐀好");
+    }
+
+    #[test]
+    pub fn test_alternatives() {
+        let alts = alternatives('发', false);
+        assert!(alts.contains(&"發".to_string()));
+        assert!(alts.contains(&"髮".to_string()));
+    }
+
+    #[test]
+    pub fn test_detect() {
+        assert_eq!(
+            ScriptCounts::detect("头发很长").script(),
+            Script::Simplified
+        );
+        assert_eq!(
+            ScriptCounts::detect("頭髮很長").script(),
+            Script::Traditional
+        );
+        assert_eq!(ScriptCounts::detect("一二三").script(), Script::Unknown);
+        assert_eq!(ScriptCounts::detect("头发頭髮").script(), Script::Mixed);
+    }
+}
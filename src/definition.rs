@@ -0,0 +1,160 @@
+use chinese_dictionary as cd;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{measure, phonetic as ph, words::lookup};
+
+/// A reference to another entry inside a definition, like 說|说[shuo1].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub traditional: String,
+    pub simplified: String,
+    /// Numbered pinyin, if the reference gives one.
+    pub pinyin: Option<String>,
+}
+
+impl Reference {
+    pub fn text(&self, simplified: bool) -> &str {
+        if simplified {
+            &self.simplified
+        } else {
+            &self.traditional
+        }
+    }
+
+    /// The entries the reference points to, the ones with a matching
+    /// reading if it has one.
+    pub fn entries(&self) -> Vec<&'static cd::WordEntry> {
+        let mut defs = lookup(&self.simplified, false);
+        if defs.is_empty() {
+            defs = lookup(&self.traditional, true);
+        }
+        if let Some(pinyin) = &self.pinyin {
+            let matching = defs
+                .iter()
+                .copied()
+                .filter(|we| we.pinyin_numbers.eq_ignore_ascii_case(pinyin))
+                .collect::<Vec<_>>();
+            if !matching.is_empty() {
+                return matching;
+            }
+        }
+        defs
+    }
+
+    /// Tone-marked pinyin, taken from the dictionary when the reference
+    /// doesn't give one.
+    pub fn pinyin_marks(&self) -> Option<String> {
+        match &self.pinyin {
+            Some(pinyin) => Some(ph::tone_marks(pinyin)),
+            None => self.entries().first().map(|we| we.pinyin_marks.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SensePart {
+    Text(String),
+    Ref(Reference),
+    /// Pinyin on its own, like the [han4] in "Taiwan pr. [han4]".
+    Pinyin(String),
+}
+
+/// Splits a sense into plain text, references to other entries and inline
+/// pinyin. Classifier lists are left out, see `measure::classifiers`.
+pub fn parse_sense(sense: &str) -> Vec<SensePart> {
+    static REGEX: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(concat!(
+            r"([\p{Han}〇]+)(?:\|([\p{Han}〇]+))?(?:\[([A-Za-z:]+[1-5]?(?: [A-Za-z:]+[1-5]?)*)\])?",
+            r"|\[([A-Za-z:]+[1-5](?: [A-Za-z:]+[1-5])*)\]",
+        ))
+        .expect("Internal error: Could not compile regex")
+    });
+    let sense = measure::strip_classifiers(sense);
+    let mut result = Vec::new();
+    let mut offset = 0;
+
+    for cap in REGEX.captures_iter(&sense) {
+        let m = cap.get(0).expect("Internal error: No match");
+        if m.start() > offset {
+            result.push(SensePart::Text(sense[offset..m.start()].to_owned()));
+        }
+        offset = m.end();
+        if let Some(pinyin) = cap.get(4) {
+            result.push(SensePart::Pinyin(pinyin.as_str().to_owned()));
+            continue;
+        }
+        // CC-CEDICT writes Traditional first, Simplified after the bar.
+        let first = cap[1].to_owned();
+        result.push(SensePart::Ref(Reference {
+            simplified: cap
+                .get(2)
+                .map_or_else(|| first.clone(), |m| m.as_str().to_owned()),
+            traditional: first,
+            pinyin: cap.get(3).map(|m| m.as_str().to_owned()),
+        }));
+    }
+    if offset < sense.len() {
+        result.push(SensePart::Text(sense[offset..].to_owned()));
+    }
+    result
+}
+
+/// A sense as text, with references in the given script and pinyin with
+/// tone marks.
+pub fn render_sense(sense: &str, simplified: bool) -> String {
+    parse_sense(sense)
+        .into_iter()
+        .map(|part| match part {
+            SensePart::Text(text) => text,
+            SensePart::Pinyin(pinyin) => ph::tone_marks(&pinyin),
+            SensePart::Ref(r) => match r.pinyin_marks() {
+                Some(marks) => format!("{} ({marks})", r.text(simplified)),
+                None => r.text(simplified).to_owned(),
+            },
+        })
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_sense() {
+        let parts = parse_sense("variant of 說|说[shuo1]");
+        assert_eq!(parts[0], SensePart::Text("variant of ".to_owned()));
+        assert_eq!(
+            parts[1],
+            SensePart::Ref(Reference {
+                traditional: "說".to_owned(),
+                simplified: "说".to_owned(),
+                pinyin: Some("shuo1".to_owned()),
+            })
+        );
+        assert_eq!(
+            parse_sense("Taiwan pr. [han4]")[1],
+            SensePart::Pinyin("han4".to_owned())
+        );
+    }
+
+    #[test]
+    pub fn test_render_sense() {
+        assert_eq!(
+            render_sense("variant of 說|说[shuo1]", false),
+            "variant of 說 (shuō)"
+        );
+        assert_eq!(
+            render_sense("abbr. for 書經|书经[Shu1 jing1]", true),
+            "abbr. for 书经 (Shū jīng)"
+        );
+        assert_eq!(
+            render_sense("see 嗎啡|吗啡, morphine", true),
+            "see 吗啡 (mǎ fēi), morphine"
+        );
+        assert_eq!(render_sense("Taiwan pr. [han4]", true), "Taiwan pr. hàn");
+        assert_eq!(render_sense("cat (CL:隻|只[zhi1])", true), "cat");
+    }
+}
@@ -0,0 +1,512 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+use chinese_dictionary as cd;
+use dioxus::prelude::*;
+use futures::prelude::*;
+use once_cell::sync::Lazy;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{
+    input::{read_file_input, InputAction},
+    phonetic as ph,
+};
+
+/// Where the web version looks for a CC-CEDICT file by default, relative to
+/// the page.
+pub const DEFAULT_CEDICT_URL: &str = "cedict_ts.u8";
+
+/// Longest word the CC-CEDICT tokenizer tries to match.
+const MAX_WORD_LEN: usize = 8;
+
+/// First `word_id` of CC-CEDICT entries, above those of the embedded
+/// dictionary so entries of both never compare equal.
+const CEDICT_FIRST_ID: u32 = 1 << 31;
+
+/// A source of dictionary entries. Entries are `'static` since segments
+/// keep references to them for as long as the text is shown.
+pub trait Dictionary: Send + Sync {
+    /// Shown in the settings, like "CC-CEDICT (120000 entries)".
+    fn name(&self) -> String;
+
+    /// Entries for an exact Simplified word.
+    fn query_by_simplified(&self, word: &str) -> Vec<&'static cd::WordEntry>;
+
+    /// Entries for an exact Traditional word.
+    fn query_by_traditional(&self, word: &str) -> Vec<&'static cd::WordEntry>;
+
+    /// Entries for space separated pinyin keys, each written without spaces
+    /// and either toneless ("zhongguo"), numbered ("zhong1guo2") or marked
+    /// ("zhōngguó"), with ü as u:.
+    fn query_by_pinyin(&self, keys: &str) -> Vec<&'static cd::WordEntry>;
+
+    /// Entries with an English keyword or phrase in their senses.
+    fn query_by_english(&self, s: &str) -> Vec<&'static cd::WordEntry>;
+
+    /// Splits a run of Han characters into words. Characters may be left
+    /// out, see `words::tokenize`.
+    fn tokenize<'a>(&self, s: &'a str) -> Vec<&'a str>;
+
+    /// Tokenizes the text and looks up each word in its script.
+    fn query_by_chinese(&self, s: &str) -> Vec<&'static cd::WordEntry> {
+        let traditional = cd::is_traditional(s);
+        self.tokenize(s)
+            .into_iter()
+            .flat_map(|word| {
+                if traditional {
+                    self.query_by_traditional(word)
+                } else {
+                    self.query_by_simplified(word)
+                }
+            })
+            .collect()
+    }
+}
+
+/// The dictionary built into the `chinese_dictionary` crate.
+pub struct Embedded;
+
+impl Dictionary for Embedded {
+    fn name(&self) -> String {
+        "built-in".to_owned()
+    }
+
+    fn query_by_simplified(&self, word: &str) -> Vec<&'static cd::WordEntry> {
+        cd::query_by_simplified(word)
+    }
+
+    fn query_by_traditional(&self, word: &str) -> Vec<&'static cd::WordEntry> {
+        cd::query_by_traditional(word)
+    }
+
+    fn query_by_pinyin(&self, keys: &str) -> Vec<&'static cd::WordEntry> {
+        cd::query_by_pinyin(keys)
+    }
+
+    fn query_by_english(&self, s: &str) -> Vec<&'static cd::WordEntry> {
+        cd::query_by_english(s)
+    }
+
+    fn tokenize<'a>(&self, s: &'a str) -> Vec<&'a str> {
+        cd::tokenize(s)
+    }
+
+    fn query_by_chinese(&self, s: &str) -> Vec<&'static cd::WordEntry> {
+        cd::query_by_chinese(s)
+    }
+}
+
+type Index = HashMap<String, Vec<u32>>;
+
+/// A dictionary parsed from a CC-CEDICT text file.
+pub struct Cedict {
+    entries: &'static [cd::WordEntry],
+    simplified: Index,
+    traditional: Index,
+    pinyin: Index,
+    /// Whole senses, like "to run".
+    phrases: Index,
+    /// Single words of senses.
+    keywords: Index,
+    /// Lines that weren't entries.
+    skipped: usize,
+}
+
+fn add(index: &mut Index, key: String, id: u32) {
+    let ids = index.entry(key).or_default();
+    if ids.last() != Some(&id) {
+        ids.push(id);
+    }
+}
+
+/// Lowercase words of a sense, without punctuation or parentheticals.
+fn sense_words(sense: &str) -> Vec<String> {
+    let mut depth = 0usize;
+    let plain = sense
+        .chars()
+        .filter(|c| match c {
+            '(' => {
+                depth += 1;
+                false
+            }
+            ')' => {
+                depth = depth.saturating_sub(1);
+                false
+            }
+            _ => depth == 0,
+        })
+        .collect::<String>();
+    plain
+        .split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '-')
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Parses a line like `傳統 传统 [chuan2 tong3] /tradition/convention/`.
+fn parse_line(line: &str, word_id: u32) -> Option<cd::WordEntry> {
+    let (traditional, rest) = line.split_once(' ')?;
+    let (simplified, rest) = rest.split_once(" [")?;
+    let (pinyin_numbers, rest) = rest.split_once("] /")?;
+    let english = rest
+        .trim_end()
+        .strip_suffix('/')?
+        .split('/')
+        .filter(|sense| !sense.is_empty())
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    let tone_marks = pinyin_numbers
+        .split(' ')
+        .map(|syl| {
+            syl.chars()
+                .last()
+                .and_then(|c| c.to_digit(10))
+                .map_or(5, |tone| tone as u8)
+        })
+        .collect();
+    let mut hasher = DefaultHasher::new();
+    line.hash(&mut hasher);
+
+    Some(cd::WordEntry {
+        traditional: traditional.to_owned(),
+        simplified: simplified.to_owned(),
+        pinyin_marks: ph::tone_marks(pinyin_numbers),
+        pinyin_numbers: pinyin_numbers.to_owned(),
+        english,
+        tone_marks,
+        hash: hasher.finish(),
+        // Classifiers stay in the senses, see `measure::classifiers`.
+        measure_words: Vec::new(),
+        // HSK levels aren't part of CC-CEDICT, see `hsk_level`.
+        hsk: 0,
+        word_id,
+    })
+}
+
+/// HSK level of an entry of any dictionary. Levels of CC-CEDICT entries
+/// come from the same word in the built-in dictionary, looked up when
+/// they're needed rather than for every line of the file.
+pub fn hsk_level(we: &cd::WordEntry) -> u8 {
+    if we.word_id < CEDICT_FIRST_ID {
+        return we.hsk;
+    }
+    cd::query_by_simplified(&we.simplified)
+        .into_iter()
+        .find(|other| {
+            other
+                .pinyin_numbers
+                .eq_ignore_ascii_case(&we.pinyin_numbers)
+        })
+        .map_or(0, |other| other.hsk)
+}
+
+impl Cedict {
+    /// Parses CC-CEDICT text, skipping comments and counting lines that
+    /// aren't entries. The entries are leaked since segments point into
+    /// them, see `load_cedict` for how reloading avoids that.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut entries = Vec::new();
+        let mut skipped = 0;
+        for line in text.lines() {
+            let line = line.trim_start_matches('\u{feff}').trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_line(line, CEDICT_FIRST_ID + entries.len() as u32) {
+                Some(we) => entries.push(we),
+                None => skipped += 1,
+            }
+        }
+        if entries.is_empty() {
+            return Err("No entries in dictionary".to_owned());
+        }
+        Ok(Self::new(Vec::leak(entries), skipped))
+    }
+
+    fn new(entries: &'static [cd::WordEntry], skipped: usize) -> Self {
+        let mut dict = Self {
+            entries,
+            simplified: Index::new(),
+            traditional: Index::new(),
+            pinyin: Index::new(),
+            phrases: Index::new(),
+            keywords: Index::new(),
+            skipped,
+        };
+        for (id, we) in entries.iter().enumerate() {
+            let id = id as u32;
+            add(&mut dict.simplified, we.simplified.clone(), id);
+            add(&mut dict.traditional, we.traditional.clone(), id);
+
+            let numbers = we.pinyin_numbers.to_lowercase().replace(' ', "");
+            let toneless = numbers.replace(|c: char| c.is_ascii_digit(), "");
+            let marks = we.pinyin_marks.to_lowercase().replace(' ', "");
+            add(&mut dict.pinyin, numbers, id);
+            add(&mut dict.pinyin, toneless, id);
+            add(&mut dict.pinyin, marks, id);
+
+            for sense in we.english.iter() {
+                let words = sense_words(sense);
+                for word in words.iter() {
+                    add(&mut dict.keywords, word.clone(), id);
+                }
+                add(&mut dict.phrases, words.join(" "), id);
+            }
+        }
+        dict
+    }
+
+    fn get(&self, index: &Index, key: &str) -> Vec<&'static cd::WordEntry> {
+        index
+            .get(key)
+            .map(|ids| ids.iter().map(|id| &self.entries[*id as usize]).collect())
+            .unwrap_or_default()
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        self.simplified.contains_key(word) || self.traditional.contains_key(word)
+    }
+}
+
+impl Dictionary for Cedict {
+    fn name(&self) -> String {
+        match self.skipped {
+            0 => format!("CC-CEDICT ({} entries)", self.entries.len()),
+            skipped => format!(
+                "CC-CEDICT ({} entries, {skipped} bad lines skipped)",
+                self.entries.len()
+            ),
+        }
+    }
+
+    fn query_by_simplified(&self, word: &str) -> Vec<&'static cd::WordEntry> {
+        self.get(&self.simplified, word)
+    }
+
+    fn query_by_traditional(&self, word: &str) -> Vec<&'static cd::WordEntry> {
+        self.get(&self.traditional, word)
+    }
+
+    fn query_by_pinyin(&self, keys: &str) -> Vec<&'static cd::WordEntry> {
+        keys.split(' ')
+            .flat_map(|key| self.get(&self.pinyin, &key.to_lowercase()))
+            .collect()
+    }
+
+    fn query_by_english(&self, s: &str) -> Vec<&'static cd::WordEntry> {
+        let words = sense_words(s);
+        // Senses that are exactly the search, then ones with all its words.
+        let mut result = self.get(&self.phrases, &words.join(" "));
+        let mut ids = match words.first().and_then(|w| self.keywords.get(w)) {
+            Some(ids) => ids.clone(),
+            None => return result,
+        };
+        for word in words.iter().skip(1) {
+            let other = self
+                .keywords
+                .get(word)
+                .map(|ids| ids.iter().collect::<HashSet<_>>())
+                .unwrap_or_default();
+            ids.retain(|id| other.contains(id));
+        }
+        let mut seen = result.iter().map(|we| we.word_id).collect::<HashSet<_>>();
+        for id in ids {
+            let we = &self.entries[id as usize];
+            if seen.insert(we.word_id) {
+                result.push(we);
+            }
+        }
+        result
+    }
+
+    /// Longest match first, characters it doesn't know are their own words.
+    fn tokenize<'a>(&self, s: &'a str) -> Vec<&'a str> {
+        let starts = s.char_indices().map(|(idx, _)| idx).collect::<Vec<_>>();
+        let mut result = Vec::new();
+        let mut start = 0;
+        while start < starts.len() {
+            let byteend = |end: usize| starts.get(end).copied().unwrap_or(s.len());
+            let end = (start + 2..=(start + MAX_WORD_LEN).min(starts.len()))
+                .rev()
+                .find(|end| self.contains(&s[starts[start]..byteend(*end)]))
+                .unwrap_or(start + 1);
+            result.push(&s[starts[start]..byteend(end)]);
+            start = end;
+        }
+        result
+    }
+}
+
+static ACTIVE: Lazy<RwLock<Arc<dyn Dictionary>>> = Lazy::new(|| RwLock::new(Arc::new(Embedded)));
+
+/// The dictionary all lookups go through.
+pub fn dictionary() -> Arc<dyn Dictionary> {
+    ACTIVE
+        .read()
+        .expect("Internal error: Dictionary lock poisoned")
+        .clone()
+}
+
+/// Bumped whenever the dictionary is replaced.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Changes when the dictionary does, so results of lookups can be cached.
+pub fn generation() -> usize {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+/// Replaces the dictionary. Entries it returned stay valid, see
+/// `Cedict::parse`.
+pub fn set_dictionary(dict: Arc<dyn Dictionary>) {
+    *ACTIVE
+        .write()
+        .expect("Internal error: Dictionary lock poisoned") = dict;
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A CC-CEDICT dictionary with a hash of its text.
+type Loaded = (u64, Arc<Cedict>);
+
+/// The last CC-CEDICT file loaded.
+static LOADED: Lazy<Mutex<Option<Loaded>>> = Lazy::new(|| Mutex::new(None));
+
+/// Parses a CC-CEDICT file, reusing the last one loaded if it's the same
+/// file so switching back and forth doesn't leak its entries again.
+pub fn load_cedict(text: &str) -> Result<Arc<Cedict>, String> {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    let hash = hasher.finish();
+    let mut loaded = LOADED
+        .lock()
+        .expect("Internal error: Dictionary lock poisoned");
+    match &*loaded {
+        Some((other, dict)) if *other == hash => Ok(dict.clone()),
+        _ => {
+            let dict = Arc::new(Cedict::parse(text)?);
+            *loaded = Some((hash, dict.clone()));
+            Ok(dict)
+        }
+    }
+}
+
+pub enum DictionaryAction {
+    /// Loads a CC-CEDICT file from a URL, like one next to the page.
+    Fetch(String),
+    /// Loads the CC-CEDICT file chosen in the upload input.
+    Upload,
+    /// Switches back to the built-in dictionary.
+    Embedded,
+}
+
+async fn fetch_text(url: &str) -> Result<String, String> {
+    let failed = |_| format!("Could not load {url}");
+    let window = web_sys::window().ok_or("No window")?;
+    let resp: web_sys::Response = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .and_then(|resp| resp.dyn_into())
+        .map_err(failed)?;
+    if !resp.ok() {
+        return Err(format!("Could not load {url}: {}", resp.status()));
+    }
+    JsFuture::from(resp.text().map_err(failed)?)
+        .await
+        .map_err(failed)?
+        .as_string()
+        .ok_or_else(|| format!("Could not load {url}"))
+}
+
+pub async fn dictionary_service(
+    mut rx: UnboundedReceiver<DictionaryAction>,
+    status: UseRef<String>,
+    input_task: CoroutineHandle<InputAction>,
+) {
+    while let Some(msg) = rx.next().await {
+        status.set("Loading...".to_owned());
+        // Let the status show before parsing blocks for a while.
+        gloo_timers::future::TimeoutFuture::new(0).await;
+        let text = match msg {
+            DictionaryAction::Fetch(url) => fetch_text(&url).await,
+            DictionaryAction::Upload => read_file_input("dictfile").await.map(|(_, text)| text),
+            DictionaryAction::Embedded => {
+                set_dictionary(Arc::new(Embedded));
+                status.set(dictionary().name());
+                input_task.send(InputAction::Refresh);
+                continue;
+            }
+        };
+        match text.and_then(|text| load_cedict(&text)) {
+            Ok(dict) => {
+                set_dictionary(dict);
+                status.set(dictionary().name());
+                input_task.send(InputAction::Refresh);
+            }
+            Err(err) => status.set(format!("{err}, using {}", dictionary().name())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const STUB: &str = include_str!("../test_data/cedict_stub.u8");
+
+    #[test]
+    pub fn test_parse_cedict() {
+        let dict = Cedict::parse(STUB).unwrap();
+        assert_eq!(dict.name(), "CC-CEDICT (9 entries)");
+        let we = dict.query_by_traditional("傳統")[0];
+        assert_eq!(we.simplified, "传统");
+        assert_eq!(we.pinyin_marks, "chuán tǒng");
+        assert_eq!(we.tone_marks, vec![2, 3]);
+        assert_eq!(we.english, vec!["tradition", "traditional", "convention"]);
+        assert_eq!(dict.query_by_simplified("女")[0].pinyin_numbers, "nu:3");
+        assert!(Cedict::parse("# only a comment\n").is_err());
+        assert!(Cedict::parse("not an entry").is_err());
+        let dict = Cedict::parse(&format!("{STUB}not an entry\n")).unwrap();
+        assert_eq!(dict.name(), "CC-CEDICT (9 entries, 1 bad lines skipped)");
+        // Entries never share an id with the embedded dictionary.
+        let ours = dict.query_by_simplified("女")[0];
+        assert!(cd::query_by_simplified("女")
+            .iter()
+            .all(|we| we.word_id != ours.word_id));
+    }
+
+    #[test]
+    pub fn test_query_cedict() {
+        let dict = Cedict::parse(STUB).unwrap();
+        let words = |defs: Vec<&'static cd::WordEntry>| {
+            defs.into_iter()
+                .map(|we| we.simplified.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(words(dict.query_by_pinyin("zhongguo")), vec!["中国"]);
+        assert_eq!(words(dict.query_by_pinyin("zhong1guo2")), vec!["中国"]);
+        assert_eq!(words(dict.query_by_pinyin("zhōngguó")), vec!["中国"]);
+        assert_eq!(words(dict.query_by_pinyin("nu:3")), vec!["女"]);
+        assert_eq!(words(dict.query_by_english("cat")), vec!["猫", "熊猫"]);
+        assert_eq!(words(dict.query_by_english("big cat")), vec!["熊猫"]);
+        assert_eq!(dict.tokenize("我是中国人"), vec!["我", "是", "中国人"]);
+        assert_eq!(dict.tokenize("我們是中國"), vec!["我們", "是", "中國"]);
+        assert_eq!(words(dict.query_by_chinese("中國人")), vec!["中国人"]);
+        let we = dict.query_by_simplified("中国")[0];
+        assert_eq!(we.hsk, 0);
+        assert_eq!(hsk_level(we), cd::query_by_simplified("中国")[0].hsk);
+        assert!(hsk_level(we) > 0);
+    }
+
+    #[test]
+    pub fn test_load_cedict() {
+        let dict = load_cedict(STUB).unwrap();
+        assert!(Arc::ptr_eq(&dict, &load_cedict(STUB).unwrap()));
+        let other = load_cedict(&format!("{STUB}\n")).unwrap();
+        assert!(!Arc::ptr_eq(&dict, &other));
+    }
+}
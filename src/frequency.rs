@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+
+/// Rank of each of the most frequent words, counting from 1.
+static RANKS: Lazy<HashMap<&'static str, usize>> = Lazy::new(|| {
+    include_str!("../assets/frequency.txt")
+        .lines()
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .enumerate()
+        .map(|(idx, word)| (word, idx + 1))
+        .collect()
+});
+
+/// Frequency rank of a word in Simplified characters, 1 for the most
+/// frequent. `None` for words rarer than the ones in the list.
+pub fn rank(simplified: &str) -> Option<usize> {
+    RANKS.get(simplified).copied()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_rank() {
+        assert_eq!(rank("了"), Some(1));
+        assert!(rank("学习") < rank("猫"));
+        assert_eq!(rank("學習"), None);
+        assert_eq!(rank("画蛇添足"), None);
+    }
+}
@@ -0,0 +1,302 @@
+use crate::{
+    pos::{self, Pos},
+    words::Segment,
+};
+
+/// A grammar construction made up of words that appear in order within a
+/// sentence, not necessarily next to each other.
+pub struct Pattern {
+    pub name: &'static str,
+    /// Each part matches any one of its words, Simplified or Traditional.
+    pub parts: &'static [&'static [&'static str]],
+    pub explanation: &'static str,
+    /// Rules out unrelated uses of the words, see `Check`.
+    pub check: Option<Check>,
+}
+
+/// Whether words found for a pattern really are the construction, given
+/// the segments, the indexes of the matched words and the end of the
+/// sentence.
+pub type Check = fn(&[Segment], &[usize], usize) -> bool;
+
+pub static PATTERNS: &[Pattern] = &[
+    Pattern {
+        name: "把",
+        parts: &[&["把"]],
+        explanation:
+            "把 + object + verb: moves the object before the verb to say what is done to it.",
+        check: Some(check_ba),
+    },
+    Pattern {
+        name: "被",
+        parts: &[&["被"]],
+        explanation: "被 (+ agent) + verb: passive voice, the subject is affected by the action.",
+        check: Some(check_bei),
+    },
+    Pattern {
+        name: "是…的",
+        parts: &[&["是"], &["的"]],
+        explanation: "Emphasizes when, where or how something that already happened was done.",
+        check: Some(check_shi_de),
+    },
+    Pattern {
+        name: "越…越",
+        parts: &[&["越"], &["越"]],
+        explanation: "The more ..., the more ...",
+        check: None,
+    },
+    Pattern {
+        name: "越来越",
+        parts: &[&["越来越", "越來越"]],
+        explanation: "越来越 + adjective: more and more, increasingly.",
+        check: None,
+    },
+    Pattern {
+        name: "一…就",
+        parts: &[&["一"], &["就"]],
+        explanation: "As soon as ..., then ...",
+        check: Some(check_yi_jiu),
+    },
+    Pattern {
+        name: "虽然…但是",
+        parts: &[&["虽然", "雖然"], &["但是", "可是", "但", "却", "卻"]],
+        explanation: "Although ..., (but) ...",
+        check: None,
+    },
+    Pattern {
+        name: "因为…所以",
+        parts: &[&["因为", "因為"], &["所以"]],
+        explanation: "Because ..., therefore ...",
+        check: None,
+    },
+    Pattern {
+        name: "不但…而且",
+        parts: &[&["不但", "不仅", "不僅"], &["而且", "也", "还", "還"]],
+        explanation: "Not only ..., but also ...",
+        check: None,
+    },
+    Pattern {
+        name: "如果…就",
+        parts: &[&["如果", "要是", "假如"], &["就", "那么", "那麼"]],
+        explanation: "If ..., then ...",
+        check: None,
+    },
+    Pattern {
+        name: "只要…就",
+        parts: &[&["只要"], &["就"]],
+        explanation: "As long as ..., then ...",
+        check: None,
+    },
+    Pattern {
+        name: "只有…才",
+        parts: &[&["只有"], &["才"]],
+        explanation: "Only if ..., then ...",
+        check: None,
+    },
+    Pattern {
+        name: "除了…以外",
+        parts: &[&["除了"], &["以外", "之外"]],
+        explanation: "Apart from ..., besides ...",
+        check: None,
+    },
+    Pattern {
+        name: "连…都/也",
+        parts: &[&["连", "連"], &["都", "也"]],
+        explanation: "Even ...: emphasizes something surprising.",
+        check: None,
+    },
+    Pattern {
+        name: "又…又",
+        parts: &[&["又"], &["又"]],
+        explanation: "Both ... and ...",
+        check: None,
+    },
+    Pattern {
+        name: "一边…一边",
+        parts: &[&["一边", "一邊"], &["一边", "一邊"]],
+        explanation: "Doing two actions at the same time.",
+        check: None,
+    },
+    Pattern {
+        name: "不是…而是",
+        parts: &[&["不是"], &["而是"]],
+        explanation: "Not ..., but rather ...",
+        check: None,
+    },
+    Pattern {
+        name: "即使…也",
+        parts: &[&["即使", "就算"], &["也"]],
+        explanation: "Even if ..., still ...",
+        check: None,
+    },
+    Pattern {
+        name: "先…然后",
+        parts: &[&["先"], &["然后", "然後", "再"]],
+        explanation: "First ..., then ...",
+        check: None,
+    },
+];
+
+/// A pattern found in the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternMatch {
+    /// Index into `PATTERNS`.
+    pub pattern: usize,
+    /// Indexes of the segments that make up the pattern.
+    pub segments: Vec<usize>,
+}
+
+impl PatternMatch {
+    pub fn pattern(&self) -> &'static Pattern {
+        &PATTERNS[self.pattern]
+    }
+}
+
+fn ends_sentence(seg: &Segment) -> bool {
+    match seg {
+        Segment::Break => true,
+        Segment::Punctuation(p) => p.contains(['。', '！', '？', '!', '?', '；', ';']),
+        _ => false,
+    }
+}
+
+fn word_matches(seg: &Segment, words: &[&str]) -> bool {
+    seg.as_chinese()
+        .and_then(|defs| defs.first())
+        .map(|we| {
+            words.contains(&we.simplified.as_str()) || words.contains(&we.traditional.as_str())
+        })
+        .unwrap_or(false)
+}
+
+fn is_verb(seg: &Segment) -> bool {
+    match seg {
+        Segment::Chinese(w) => w.pos == Pos::Verb || pos::candidates(&w.defs).contains(&Pos::Verb),
+        _ => false,
+    }
+}
+
+/// The next segment that isn't whitespace.
+fn next_segment(segments: &[Segment], idx: usize, end: usize) -> Option<&Segment> {
+    segments[idx + 1..end]
+        .iter()
+        .find(|seg| !matches!(seg, Segment::Whitespace(_)))
+}
+
+/// 把 as a measure word, like 一把伞, comes after a number or 这/那.
+fn check_ba(segments: &[Segment], matched: &[usize], end: usize) -> bool {
+    let before = matched[0].checked_sub(1).map(|idx| &segments[idx]);
+    let counted = matches!(before, Some(seg) if matches!(seg, Segment::Number(_))
+        || word_matches(seg, &["一", "两", "几", "这", "那", "兩", "幾", "這"]));
+    !counted && check_bei(segments, matched, end)
+}
+
+/// A verb has to follow in the sentence.
+fn check_bei(segments: &[Segment], matched: &[usize], end: usize) -> bool {
+    segments[matched[0] + 1..end].iter().any(is_verb)
+}
+
+/// 的 ends the clause with a verb phrase since 是, unlike 这是我的书.
+fn check_shi_de(segments: &[Segment], matched: &[usize], end: usize) -> bool {
+    let ends_clause = match next_segment(segments, matched[1], end) {
+        None => true,
+        Some(seg) => ends_sentence(seg) || matches!(seg, Segment::Punctuation(_)),
+    };
+    ends_clause && segments[matched[0] + 1..matched[1]].iter().any(is_verb)
+}
+
+/// 一 is followed by a verb, unlike the number in 一个人.
+fn check_yi_jiu(segments: &[Segment], matched: &[usize], end: usize) -> bool {
+    matches!(next_segment(segments, matched[0], end), Some(seg) if is_verb(seg))
+}
+
+/// Finds grammar patterns within each sentence of the segmented text.
+pub fn find_patterns(segments: &[Segment]) -> Vec<PatternMatch> {
+    let mut result = Vec::new();
+    let mut start = 0;
+
+    while start < segments.len() {
+        let end = segments[start..]
+            .iter()
+            .position(ends_sentence)
+            .map(|pos| start + pos)
+            .unwrap_or(segments.len());
+        for (pidx, pattern) in PATTERNS.iter().enumerate() {
+            find_in_sentence(segments, start, end, pidx, pattern, &mut result);
+        }
+        start = end + 1;
+    }
+    result
+}
+
+fn find_in_sentence(
+    segments: &[Segment],
+    start: usize,
+    end: usize,
+    pidx: usize,
+    pattern: &Pattern,
+    result: &mut Vec<PatternMatch>,
+) {
+    let mut from = start;
+    while from < end {
+        let mut matched = Vec::with_capacity(pattern.parts.len());
+        let mut pos = from;
+        for part in pattern.parts {
+            match (pos..end).find(|idx| word_matches(&segments[*idx], part)) {
+                Some(idx) => {
+                    matched.push(idx);
+                    pos = idx + 1;
+                }
+                None => return,
+            }
+        }
+        // Other uses of the first word are tried again from the next one.
+        if let Some(check) = pattern.check {
+            if !check(segments, &matched, end) {
+                from = matched[0] + 1;
+                continue;
+            }
+        }
+        from = pos;
+        result.push(PatternMatch {
+            pattern: pidx,
+            segments: matched,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{levels::LevelSystem, words::make_words};
+
+    #[test]
+    pub fn test_patterns() {
+        let (segs, _) = make_words(
+            "虽然下雨，但是他越跑越快。我把书放在桌子上。",
+            false,
+            LevelSystem::Hsk2,
+        );
+        let found = find_patterns(&segs)
+            .iter()
+            .map(|m| m.pattern().name)
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec!["越…越", "虽然…但是", "把"]);
+
+        let found = |s: &str| {
+            let (segs, _) = make_words(s, false, LevelSystem::Hsk2);
+            find_patterns(&segs)
+                .iter()
+                .map(|m| m.pattern().name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(found("他是昨天来的。"), vec!["是…的"]);
+        assert_eq!(found("这是我的书，是他买的。"), vec!["是…的"]);
+        assert!(found("这是我的书。").is_empty());
+        assert!(found("这本书是我的。").is_empty());
+        assert_eq!(found("我一回家就吃饭。"), vec!["一…就"]);
+        assert!(found("一个人就够了。").is_empty());
+        assert!(found("我买了一把伞。").is_empty());
+        assert_eq!(found("他被老师批评了。"), vec!["被"]);
+    }
+}
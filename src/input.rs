@@ -7,6 +7,10 @@ use std::pin::Pin;
 use dioxus::prelude::*;
 
 use crate::{
+    config::Config,
+    convert::{convert, Script},
+    grammar::{find_patterns, PatternMatch},
+    particles::{check_particles, Finding},
     stats::Stats,
     words::{make_words, Segment},
 };
@@ -18,17 +22,38 @@ pub fn get_textarea() -> Option<web_sys::HtmlTextAreaElement> {
         .and_then(|el| wasm_bindgen::JsCast::dyn_into(el).ok())
 }
 
+/// Reads the file chosen in a file input, returning its name and text.
+pub async fn read_file_input(id: &str) -> Result<(String, String), String> {
+    let file = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.get_element_by_id(id))
+        .and_then(|el| wasm_bindgen::JsCast::dyn_into::<web_sys::HtmlInputElement>(el).ok())
+        .and_then(|input| input.files())
+        .and_then(|files| files.get(0))
+        .ok_or("No file chosen")?;
+    wasm_bindgen_futures::JsFuture::from(file.text())
+        .await
+        .ok()
+        .and_then(|text| text.as_string())
+        .map(|text| (file.name(), text))
+        .ok_or_else(|| format!("Could not read {}", file.name()))
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
 pub enum InputAction {
     Set { refresh: bool, s: String },
     Append { refresh: bool, s: String },
+    Convert { simplified: bool },
     Refresh,
 }
 
 pub async fn input_service(
     mut rx: UnboundedReceiver<InputAction>,
+    cfg: UseRef<Config>,
     words: UseRef<Vec<Segment>>,
     stats: UseRef<Stats>,
+    patterns: UseRef<Vec<PatternMatch>>,
+    findings: UseRef<Vec<Finding>>,
 ) {
     let mknever = || Box::pin(future::pending());
     let mktimeout = |ms| {
@@ -55,6 +80,9 @@ pub async fn input_service(
                     [textbox.value().as_str(), s.as_str()].into_iter(),
                 )),
             ),
+            InputAction::Convert { simplified } => {
+                (true, Some(convert(&textbox.value(), simplified)))
+            }
             InputAction::Refresh => (true, None),
         };
 
@@ -64,7 +92,17 @@ pub async fn input_service(
         }
         if refresh {
             fut = mknever();
-            let (newwords, newstats) = make_words(&lastval);
+            let (autoscript, levels) = (cfg.read().autoscript, cfg.read().levels);
+            let (newwords, newstats) = make_words(&lastval, autoscript, levels);
+            let detected = newstats.script.script();
+            if autoscript && matches!(detected, Script::Simplified | Script::Traditional) {
+                let simplified = detected == Script::Simplified;
+                if cfg.read().simplified != simplified {
+                    cfg.write().simplified = simplified;
+                }
+            }
+            patterns.set(find_patterns(&newwords));
+            findings.set(check_particles(&newwords));
             words.set(newwords);
             stats.set(newstats);
             continue;
@@ -0,0 +1,306 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chinese_dictionary as cd;
+use dioxus::prelude::*;
+use futures::prelude::*;
+
+use crate::{convert::is_han, input::read_file_input, stats::Stats};
+
+/// Key the known words are kept under in the browser's local storage.
+const STORAGE_KEY: &str = "mandarin-webutil.known";
+
+/// How well a word is known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Familiarity {
+    Unknown,
+    Learning,
+    Known,
+}
+
+impl Familiarity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::Learning => "learning",
+            Self::Known => "known",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "unknown" => Some(Self::Unknown),
+            "learning" => Some(Self::Learning),
+            "known" => Some(Self::Known),
+            _ => None,
+        }
+    }
+
+    /// The state after clicking a word: unknown, learning, known, unknown.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Unknown => Self::Learning,
+            Self::Learning => Self::Known,
+            Self::Known => Self::Unknown,
+        }
+    }
+}
+
+/// Words the user has marked as known or learning, by their Simplified form.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KnownWords {
+    words: HashMap<String, Familiarity>,
+}
+
+impl KnownWords {
+    pub fn get(&self, word: &str) -> Familiarity {
+        self.words
+            .get(word)
+            .copied()
+            .unwrap_or(Familiarity::Unknown)
+    }
+
+    pub fn set(&mut self, word: &str, familiarity: Familiarity) {
+        if familiarity == Familiarity::Unknown {
+            self.words.remove(word);
+        } else {
+            self.words.insert(word.to_owned(), familiarity);
+        }
+    }
+
+    /// Moves a word on to its next state, returning it.
+    pub fn cycle(&mut self, word: &str) -> Familiarity {
+        let next = self.get(word).next();
+        self.set(word, next);
+        next
+    }
+
+    pub fn count(&self, familiarity: Familiarity) -> usize {
+        self.words.values().filter(|f| **f == familiarity).count()
+    }
+
+    /// Adds the words of another list, keeping the better known state.
+    pub fn merge(&mut self, other: Self) {
+        for (word, familiarity) in other.words {
+            if familiarity > self.get(&word) {
+                self.words.insert(word, familiarity);
+            }
+        }
+    }
+
+    fn sorted(&self) -> Vec<(&String, &Familiarity)> {
+        let mut words = self.words.iter().collect::<Vec<_>>();
+        words.sort_unstable();
+        words
+    }
+
+    /// A JSON object like `{"你好":"known","学习":"learning"}`.
+    pub fn to_json(&self) -> String {
+        let fields = self
+            .sorted()
+            .into_iter()
+            .map(|(word, familiarity)| (word, familiarity.name()))
+            .collect::<BTreeMap<_, _>>();
+        serde_json::to_string(&fields).expect("Internal error: Could not write JSON")
+    }
+
+    /// The known words, one per line.
+    pub fn to_list(&self) -> String {
+        self.sorted()
+            .into_iter()
+            .filter(|(_, familiarity)| **familiarity == Familiarity::Known)
+            .map(|(word, _)| format!("{word}\n"))
+            .collect()
+    }
+
+    /// Reads either the JSON of `to_json` or a plain list of known words,
+    /// separated by lines, spaces or commas.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim_start_matches('\u{feff}').trim();
+        let mut result = Self::default();
+        if text.starts_with('{') {
+            let fields = serde_json::from_str::<HashMap<String, String>>(text)
+                .map_err(|err| format!("Not a JSON object of words: {err}"))?;
+            for (word, name) in fields {
+                let familiarity = Familiarity::from_name(&name)
+                    .ok_or_else(|| format!("Unknown state \"{name}\" for {word}"))?;
+                result.set(&word, familiarity);
+            }
+        } else {
+            text.split(|c: char| c.is_whitespace() || matches!(c, ',' | '，' | '、' | ';'))
+                .filter(|word| word.chars().any(is_han))
+                .for_each(|word| result.set(word, Familiarity::Known));
+        }
+        if result.words.is_empty() {
+            return Err("No words found".to_owned());
+        }
+        Ok(result)
+    }
+
+    /// Words of a text the user knows, as (total, unique).
+    pub fn coverage(&self, stats: &Stats) -> (usize, usize) {
+        stats
+            .hskwords
+            .iter()
+            .flatten()
+            .filter(|(word, _)| self.get(word) == Familiarity::Known)
+            .fold((0, 0), |(total, unique), (_, count)| {
+                (total + count, unique + 1)
+            })
+    }
+
+    /// The words saved in local storage, if there are any.
+    pub fn load() -> Self {
+        local_storage()
+            .and_then(|storage| storage.get_item(STORAGE_KEY).ok().flatten())
+            .and_then(|json| Self::parse(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Some(storage) = local_storage() {
+            let _ = storage.set_item(STORAGE_KEY, &self.to_json());
+        }
+    }
+}
+
+/// Words whose hints were revealed in reveal mode, in the order they were.
+#[derive(Debug, Clone, Default)]
+pub struct RevealedWords {
+    words: Vec<&'static cd::WordEntry>,
+}
+
+impl RevealedWords {
+    pub fn contains(&self, we: &cd::WordEntry) -> bool {
+        self.words
+            .iter()
+            .any(|other| other.simplified == we.simplified)
+    }
+
+    pub fn reveal(&mut self, we: &'static cd::WordEntry) {
+        if !self.contains(we) {
+            self.words.push(we);
+        }
+    }
+
+    pub fn words(&self) -> &[&'static cd::WordEntry] {
+        &self.words
+    }
+
+    /// The revealed words as words being learned.
+    pub fn to_learning(&self) -> KnownWords {
+        let mut result = KnownWords::default();
+        self.words
+            .iter()
+            .for_each(|we| result.set(&we.simplified, Familiarity::Learning));
+        result
+    }
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+pub enum KnownAction {
+    /// Cycles a word through unknown, learning and known.
+    Cycle(String),
+    /// Imports the file chosen in the known words file input.
+    Upload,
+    /// Imports pasted JSON or a word list.
+    Import(String),
+    /// Marks words as learning, unless they're known.
+    Learn(KnownWords),
+    Clear,
+}
+
+/// Updates the known words and saves them after every change.
+pub async fn known_service(
+    mut rx: UnboundedReceiver<KnownAction>,
+    known: UseRef<KnownWords>,
+    status: UseRef<String>,
+) {
+    while let Some(msg) = rx.next().await {
+        let imported = match msg {
+            KnownAction::Cycle(word) => {
+                known.write().cycle(&word);
+                Ok(None)
+            }
+            KnownAction::Upload => read_file_input("knownfile")
+                .await
+                .and_then(|(_, text)| KnownWords::parse(&text))
+                .map(Some),
+            KnownAction::Import(text) => KnownWords::parse(&text).map(Some),
+            KnownAction::Learn(words) => {
+                known.write().merge(words);
+                Ok(None)
+            }
+            KnownAction::Clear => {
+                known.set(KnownWords::default());
+                Ok(None)
+            }
+        };
+        match imported {
+            Ok(Some(other)) => {
+                let count = other.words.len();
+                known.write().merge(other);
+                status.set(format!("(imported {count} words)"));
+            }
+            Ok(None) => (),
+            Err(err) => status.set(format!("({err})")),
+        }
+        known.read().save();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_known_words() {
+        let mut known = KnownWords::default();
+        assert_eq!(known.cycle("学习"), Familiarity::Learning);
+        assert_eq!(known.cycle("学习"), Familiarity::Known);
+        known.set("你好", Familiarity::Known);
+        known.set("\"引\"", Familiarity::Learning);
+        assert_eq!(known.count(Familiarity::Known), 2);
+        assert_eq!(known.to_list(), "你好\n学习\n");
+
+        let json = known.to_json();
+        assert_eq!(
+            json,
+            r#"{"\"引\"":"learning","你好":"known","学习":"known"}"#
+        );
+        assert_eq!(KnownWords::parse(&json), Ok(known.clone()));
+        let spaced = "{ \"你好\" : \"known\",\n \"学习\": \"learning\" }";
+        assert_eq!(
+            KnownWords::parse(spaced).unwrap().get("学习"),
+            Familiarity::Learning
+        );
+        assert!(KnownWords::parse("{\"你好\": 1}").is_err());
+        let escaped = r#"{"\u5b66\u4e60":"known","\ud840\udc0b\/\b\f":"known"}"#;
+        let escaped = KnownWords::parse(escaped).unwrap();
+        assert_eq!(escaped.get("学习"), Familiarity::Known);
+        assert_eq!(escaped.get("\u{2000b}/\u{8}\u{c}"), Familiarity::Known);
+        assert!(KnownWords::parse(r#"{"\ud840":"known"}"#).is_err());
+        assert!(KnownWords::parse(r#"{"\x":"known"}"#).is_err());
+        assert!(KnownWords::parse("{\"你好\": \"maybe\"}").is_err());
+
+        let list = KnownWords::parse("你好, 学习\n我").unwrap();
+        assert_eq!(list.get("我"), Familiarity::Known);
+        assert_eq!(list.get("猫"), Familiarity::Unknown);
+
+        assert_eq!(known.cycle("学习"), Familiarity::Unknown);
+        known.merge(list);
+        assert_eq!(known.get("学习"), Familiarity::Known);
+
+        let mut revealed = RevealedWords::default();
+        revealed.reveal(cd::query_by_simplified("学习")[0]);
+        revealed.reveal(cd::query_by_simplified("猫")[0]);
+        revealed.reveal(cd::query_by_simplified("学习")[0]);
+        assert_eq!(revealed.words().len(), 2);
+        assert!(revealed.contains(cd::query_by_simplified("猫")[0]));
+        known.merge(revealed.to_learning());
+        assert_eq!(known.get("学习"), Familiarity::Known);
+        assert_eq!(known.get("猫"), Familiarity::Learning);
+    }
+}
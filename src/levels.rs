@@ -0,0 +1,114 @@
+use chinese_dictionary as cd;
+
+use crate::{
+    dictionary::{dictionary, hsk_level},
+    wordlist,
+};
+
+/// A system of proficiency levels that words are graded by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelSystem {
+    /// The old six level HSK, as given by the dictionary.
+    Hsk2,
+    /// An imported word list, with lessons as levels, see `wordlist`.
+    Custom(usize),
+}
+
+impl LevelSystem {
+    pub const OPTIONS: &'static [&'static str] = &["HSK 2.0"];
+
+    /// The built-in systems followed by the imported word lists.
+    pub fn options() -> Vec<String> {
+        Self::OPTIONS
+            .iter()
+            .map(|o| (*o).to_owned())
+            .chain(wordlist::names())
+            .collect()
+    }
+
+    /// Index into `options`.
+    pub fn index(self) -> usize {
+        match self {
+            Self::Hsk2 => 0,
+            Self::Custom(idx) => Self::OPTIONS.len() + idx,
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Self::Hsk2 => "HSK".to_owned(),
+            Self::Custom(idx) => wordlist::list(idx).map_or_else(String::new, |l| l.name.clone()),
+        }
+    }
+
+    pub fn level_count(self) -> u8 {
+        match self {
+            Self::Hsk2 => 6,
+            Self::Custom(idx) => wordlist::list(idx).map_or(0, |l| l.lessons.len() as u8),
+        }
+    }
+
+    /// Level of a definition, 0 if it isn't in any.
+    pub fn level(self, we: &cd::WordEntry) -> u8 {
+        match self {
+            Self::Hsk2 => hsk_level(we),
+            Self::Custom(idx) => wordlist::list(idx).map_or(0, |l| l.level(we)),
+        }
+    }
+
+    /// Level of a character on its own, 0 if it isn't in any.
+    pub fn char_level(self, c: char) -> u8 {
+        dictionary()
+            .query_by_simplified(&c.to_string())
+            .iter()
+            .map(|we| self.level(we))
+            .filter(|level| *level > 0)
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// A level the way the system writes it, like "3" or the name of a
+    /// lesson.
+    pub fn short_label(self, level: u8) -> String {
+        match (self, level) {
+            (Self::Custom(idx), _) => {
+                wordlist::list(idx).map_or_else(String::new, |l| l.lesson_name(level).to_owned())
+            }
+            _ => level.to_string(),
+        }
+    }
+
+    /// A level with the system name, like "HSK 3". Lessons are only given
+    /// by name.
+    pub fn label(self, level: u8) -> String {
+        match self {
+            Self::Custom(_) => self.short_label(level),
+            _ => format!("{} {}", self.name(), self.short_label(level)),
+        }
+    }
+}
+
+impl From<usize> for LevelSystem {
+    fn from(val: usize) -> Self {
+        match val {
+            0 => Self::Hsk2,
+            idx => Self::Custom(idx - Self::OPTIONS.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_levels() {
+        let we = cd::query_by_simplified("学习")[0];
+        assert_eq!(LevelSystem::Hsk2.level(we), 1);
+        assert_eq!(LevelSystem::Hsk2.label(4), "HSK 4");
+        assert_eq!(LevelSystem::Hsk2.char_level('我'), 1);
+        assert_eq!(LevelSystem::from(0), LevelSystem::Hsk2);
+        assert_eq!(LevelSystem::from(1), LevelSystem::Custom(0));
+        assert_eq!(LevelSystem::Custom(0).index(), 1);
+    }
+}
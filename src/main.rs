@@ -2,23 +2,47 @@
 #[cfg(feature = "desktop")]
 const _DUMMY: () = compile_error!("Desktop feature currently non-functional");
 
+use std::collections::HashSet;
+
 use dioxus::{core::to_owned, events::FormEvent, prelude::*};
 
 use chinese_dictionary as cd;
 
+mod characters;
 mod clipboard;
 mod config;
+mod convert;
+mod definition;
+mod dictionary;
+mod frequency;
+mod grammar;
 mod input;
+mod known;
+mod levels;
+mod measure;
+mod particles;
 mod phonetic;
+mod pos;
+mod search;
 mod stats;
+mod wordlist;
 mod words;
 
 use crate::{
     config::*,
+    definition::{parse_sense, render_sense, Reference, SensePart},
+    dictionary::DictionaryAction,
+    grammar::PatternMatch,
     input::*,
+    known::{Familiarity, KnownAction, KnownWords, RevealedWords},
+    levels::LevelSystem,
+    particles::Finding,
     phonetic as ph,
+    pos::Pos,
+    search::SearchMode,
     stats::Stats,
-    words::{generate_hint, Segment},
+    wordlist::WordListAction,
+    words::{generate_hint, Number, Segment, Word},
 };
 
 static VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
@@ -37,10 +61,18 @@ fn App(cx: Scope) -> Element {
     let config = use_ref(&cx, Config::default);
     let segments = use_ref(&cx, Vec::default);
     let stats = use_ref(&cx, Stats::default);
+    let patterns = use_ref(&cx, Vec::default);
+    let findings = use_ref(&cx, Vec::default);
+    let selected = use_ref(&cx, || None::<Reference>);
+    let dictstatus = use_ref(&cx, || dictionary::dictionary().name());
+    let liststatus = use_ref(&cx, String::new);
+    let known = use_ref(&cx, KnownWords::load);
+    let knownstatus = use_ref(&cx, String::new);
+    let revealed = use_ref(&cx, RevealedWords::default);
 
     use_coroutine(&cx, {
-        to_owned![segments, stats];
-        |rx| input::input_service(rx, segments, stats)
+        to_owned![config, segments, stats, patterns, findings];
+        |rx| input::input_service(rx, config, segments, stats, patterns, findings)
     });
 
     use_coroutine(&cx, {
@@ -51,19 +83,59 @@ fn App(cx: Scope) -> Element {
     });
 
     use_coroutine(&cx, {
-        to_owned![config, segments];
-        |rx| clipboard::copy_service(rx, config, segments)
+        to_owned![config, segments, known];
+        |rx| clipboard::copy_service(rx, config, segments, known)
+    });
+
+    use_coroutine(&cx, {
+        let input_task =
+            use_coroutine_handle::<InputAction>(&cx).expect("Could not get input task");
+        to_owned![dictstatus, input_task];
+        move |rx| dictionary::dictionary_service(rx, dictstatus, input_task)
+    });
+
+    use_coroutine(&cx, {
+        let input_task =
+            use_coroutine_handle::<InputAction>(&cx).expect("Could not get input task");
+        to_owned![config, liststatus, input_task];
+        move |rx| wordlist::wordlist_service(rx, config, liststatus, input_task)
+    });
+
+    use_coroutine(&cx, {
+        to_owned![known, knownstatus];
+        |rx| known::known_service(rx, known, knownstatus)
     });
 
     cx.render(rsx! {
         style { [include_str!("../assets/styles.css")] }
         Settings { cfg: config.clone() }
+        DictionarySettings { status: dictstatus.clone() }
+        WordListSettings { status: liststatus.clone() }
+        KnownWordsSettings { known: known.clone(), status: knownstatus.clone() }
         p { }
         h3 { "Enter Simplified Chinese text:" }
-        div { ClipboardFunctions { cfg: config.clone() } }
+        div {
+            ClipboardFunctions { cfg: config.clone() }
+            ConvertInput { cfg: config.clone() }
+        }
         TextInput { }
-        SimpleStats { stats: stats.clone() }
-        PrettyChinese { cfg: config.clone(), words: segments.clone() }
+        SimpleStats { cfg: config.clone(), stats: stats.clone(), known: known.clone() }
+        CharStats { cfg: config.clone(), stats: stats.clone() }
+        PrettyChinese {
+            cfg: config.clone(),
+            words: segments.clone(),
+            patterns: patterns.clone(),
+            findings: findings.clone(),
+            selected: selected.clone(),
+            known: known.clone(),
+            revealed: revealed.clone(),
+        }
+        RevealPanel { cfg: config.clone(), revealed: revealed.clone() }
+        WordDetail { cfg: config.clone(), words: segments.clone(), selected: selected.clone() }
+        SearchPanel { cfg: config.clone(), selected: selected.clone(), known: known.clone() }
+        GrammarPanel { cfg: config.clone(), words: segments.clone(), patterns: patterns.clone() }
+        ParticlePanel { cfg: config.clone(), words: segments.clone(), findings: findings.clone() }
+        MeasureWordPanel { cfg: config.clone(), words: segments.clone() }
         p {
             small {
                 "Mandarin Webutil v{version} | "
@@ -92,7 +164,17 @@ macro_rules! cfg_toggle {
 
 #[inline_props]
 fn Settings(cx: Scope, cfg: UseRef<Config>) -> Element {
+    let input_task = use_coroutine_handle::<InputAction>(&cx).expect("Could not get input task");
     let currcfg = cfg.read();
+    let level_options = LevelSystem::options();
+    let currlevel = currcfg.levels.index();
+    let maxlevel = currcfg.levels.level_count();
+    let hintlevel = currcfg.hintlevel.min(maxlevel);
+    let hintlevel_label = if hintlevel == 0 {
+        "all words".to_owned()
+    } else {
+        currcfg.levels.label(hintlevel)
+    };
     cx.render(rsx! {
         div {
             class: "settings",
@@ -102,6 +184,14 @@ fn Settings(cx: Scope, cfg: UseRef<Config>) -> Element {
                 current: currcfg.simplified,
                 onchange: cfg_toggle!(cfg, simplified),
             }
+            BooleanOption {
+                label: "Auto script",
+                current: currcfg.autoscript,
+                onchange: move |evt| {
+                    cfg_toggle!(cfg, autoscript)(evt);
+                    input_task.send(InputAction::Refresh);
+                },
+            }
             MultiOption {
                 label: "Hint",
                 current: currcfg.hint as usize,
@@ -114,6 +204,60 @@ fn Settings(cx: Scope, cfg: UseRef<Config>) -> Element {
                     cfg.needs_update();
                 }
             }
+            MultiOption {
+                label: "Hint position",
+                current: currcfg.hintpos as usize,
+                options: HintPosition::OPTIONS,
+                onchange: |evt: FormEvent| {
+                    cfg.write().hintpos = evt.data.value.parse::<usize>().unwrap_or(1).into();
+                }
+            }
+            MultiOption {
+                label: "Second hint",
+                current: currcfg.hint2 as usize,
+                options: Hint::OPTIONS,
+                onchange: |evt: FormEvent| {
+                    cfg.write().hint2 = evt.data.value.parse::<usize>().unwrap_or(0).into();
+                }
+            }
+            MultiOption {
+                label: "Second hint position",
+                current: currcfg.hint2pos as usize,
+                options: HintPosition::OPTIONS,
+                onchange: |evt: FormEvent| {
+                    cfg.write().hint2pos = evt.data.value.parse::<usize>().unwrap_or(0).into();
+                }
+            }
+            label {
+                "Gloss length: "
+                input {
+                    r#type: "number",
+                    min: "0",
+                    size: "3",
+                    value: "{currcfg.glosslen}",
+                    oninput: |evt: FormEvent| {
+                        cfg.write().glosslen = evt.data.value.parse::<usize>().unwrap_or(0);
+                    },
+                }
+            }
+            label {
+                "Hint above: "
+                input {
+                    r#type: "range",
+                    min: "0",
+                    max: "{maxlevel}",
+                    value: "{hintlevel}",
+                    oninput: |evt: FormEvent| {
+                        cfg.write().hintlevel = evt.data.value.parse::<u8>().unwrap_or(0);
+                    },
+                }
+                " {hintlevel_label}"
+            }
+            BooleanOption {
+                label: "Reveal hints",
+                current: currcfg.revealhints,
+                onchange: cfg_toggle!(cfg, revealhints),
+            }
             BooleanOption {
                 label: "Tone colors",
                 current: currcfg.tonecolor,
@@ -124,6 +268,33 @@ fn Settings(cx: Scope, cfg: UseRef<Config>) -> Element {
                 current: currcfg.hsk,
                 onchange: cfg_toggle!(cfg, hsk),
             }
+            label {
+                "Levels: "
+                // Not a MultiOption since imported word lists are added to
+                // the options.
+                select {
+                    onchange: |evt: FormEvent| {
+                        cfg.write().levels = evt.data.value.parse::<usize>().unwrap_or(0).into();
+                        input_task.send(InputAction::Refresh);
+                    },
+                    level_options.iter().enumerate().map(|(idx, opt)| {
+                        let selected = idx == currlevel;
+                        rsx! {
+                            option { value: "{idx}", selected: "{selected}", "{opt}" }
+                        }
+                    })
+                }
+            }
+            BooleanOption {
+                label: "Known words",
+                current: currcfg.knownwords,
+                onchange: cfg_toggle!(cfg, knownwords),
+            }
+            BooleanOption {
+                label: "Click marks known",
+                current: currcfg.markknown,
+                onchange: cfg_toggle!(cfg, markknown),
+            }
             BooleanOption {
                 label: "Word spacing",
                 current: currcfg.wordspace,
@@ -134,6 +305,178 @@ fn Settings(cx: Scope, cfg: UseRef<Config>) -> Element {
                 current: currcfg.tooltips,
                 onchange: cfg_toggle!(cfg, tooltips),
             }
+            BooleanOption {
+                label: "Grammar",
+                current: currcfg.grammar,
+                onchange: cfg_toggle!(cfg, grammar),
+            }
+            BooleanOption {
+                label: "Word classes",
+                current: currcfg.poscolor,
+                onchange: cfg_toggle!(cfg, poscolor),
+            }
+            BooleanOption {
+                label: "Measure words",
+                current: currcfg.measurewords,
+                onchange: cfg_toggle!(cfg, measurewords),
+            }
+            BooleanOption {
+                label: "的/得/地",
+                current: currcfg.particles,
+                onchange: cfg_toggle!(cfg, particles),
+            }
+            MultiOption {
+                label: "Links",
+                current: currcfg.link_preset(),
+                options: LINK_NAMES,
+                onchange: |evt: FormEvent| {
+                    let preset = evt.data.value.parse::<usize>().unwrap_or(0);
+                    let mut cfg = cfg.write();
+                    match LINK_TEMPLATES.get(preset) {
+                        Some(template) => cfg.linktemplate = (*template).to_owned(),
+                        // Clearing a preset makes the template custom, a
+                        // custom one is kept.
+                        None if cfg.link_preset() != preset => cfg.linktemplate.clear(),
+                        None => (),
+                    }
+                }
+            }
+            TextOption {
+                label: "Link template",
+                current: currcfg.linktemplate.clone(),
+                oninput: |evt: FormEvent| cfg.write().linktemplate = evt.data.value.clone(),
+            }
+            BooleanOption {
+                label: "Link characters",
+                current: currcfg.linkchars,
+                onchange: cfg_toggle!(cfg, linkchars),
+            }
+        }
+    })
+}
+
+/// Exports and imports the words marked as known.
+#[inline_props]
+fn KnownWordsSettings(cx: Scope, known: UseRef<KnownWords>, status: UseRef<String>) -> Element {
+    let known_task = use_coroutine_handle::<KnownAction>(&cx).expect("Could not get known task");
+    let text = use_state(&cx, String::new);
+    let (nknown, nlearning) = (
+        known.read().count(Familiarity::Known),
+        known.read().count(Familiarity::Learning),
+    );
+    let status = status.read();
+
+    cx.render(rsx! {
+        details {
+            class: "settings",
+            summary { "Known words ({nknown} known, {nlearning} learning) {status}" }
+            small { "With \"Click marks known\" on, clicking a word cycles it through learning, known and unknown. Words are saved in this browser." }
+            br { }
+            "Import file: "
+            input {
+                id: "knownfile",
+                r#type: "file",
+                accept: ".json,.txt",
+                onchange: move |_| known_task.send(KnownAction::Upload),
+            }
+            br { }
+            textarea {
+                cols: "40",
+                rows: "5",
+                value: "{text}",
+                oninput: |evt: FormEvent| text.set(evt.data.value.clone()),
+            }
+            br { }
+            button {
+                onclick: move |_| known_task.send(KnownAction::Import(text.get().clone())),
+                "Import"
+            }
+            button { onclick: move |_| text.set(known.read().to_json()), "Export JSON" }
+            button { onclick: move |_| text.set(known.read().to_list()), "Export list" }
+            button { onclick: move |_| known_task.send(KnownAction::Clear), "Clear" }
+        }
+    })
+}
+
+/// Imports textbook word lists from a file or pasted text.
+#[inline_props]
+fn WordListSettings(cx: Scope, status: UseRef<String>) -> Element {
+    let list_task =
+        use_coroutine_handle::<WordListAction>(&cx).expect("Could not get word list task");
+    let name = use_state(&cx, String::new);
+    let text = use_state(&cx, String::new);
+    let status = status.read();
+
+    cx.render(rsx! {
+        details {
+            class: "settings",
+            summary { "Word lists {status}" }
+            small { "One word per line with \"# Lesson 1\" lines between lessons, or CSV lines like \"学习,Lesson 1\"." }
+            br { }
+            "Import file: "
+            input {
+                id: "wordlistfile",
+                r#type: "file",
+                accept: ".txt,.csv,.tsv",
+                onchange: move |_| list_task.send(WordListAction::Upload),
+            }
+            br { }
+            TextOption {
+                label: "Name",
+                current: name.get().clone(),
+                oninput: |evt: FormEvent| name.set(evt.data.value.clone()),
+            }
+            br { }
+            textarea {
+                cols: "40",
+                rows: "5",
+                value: "{text}",
+                oninput: |evt: FormEvent| text.set(evt.data.value.clone()),
+            }
+            br { }
+            button {
+                onclick: move |_| list_task.send(WordListAction::Paste {
+                    name: name.get().clone(),
+                    text: text.get().clone(),
+                }),
+                "Import"
+            }
+        }
+    })
+}
+
+/// Shows the dictionary in use and loads CC-CEDICT from a URL or an upload.
+#[inline_props]
+fn DictionarySettings(cx: Scope, status: UseRef<String>) -> Element {
+    let dict_task =
+        use_coroutine_handle::<DictionaryAction>(&cx).expect("Could not get dictionary task");
+    let url = use_state(&cx, || dictionary::DEFAULT_CEDICT_URL.to_owned());
+    let status = status.read();
+
+    cx.render(rsx! {
+        div {
+            class: "settings",
+            "Dictionary: {status} "
+            TextOption {
+                label: "CC-CEDICT URL",
+                current: url.get().clone(),
+                oninput: |evt: FormEvent| url.set(evt.data.value.clone()),
+            }
+            button {
+                onclick: move |_| dict_task.send(DictionaryAction::Fetch(url.get().clone())),
+                "Load"
+            }
+            " or file: "
+            input {
+                id: "dictfile",
+                r#type: "file",
+                accept: ".u8,.txt",
+                onchange: move |_| dict_task.send(DictionaryAction::Upload),
+            }
+            button {
+                onclick: move |_| dict_task.send(DictionaryAction::Embedded),
+                "Use built-in"
+            }
         }
     })
 }
@@ -156,26 +499,76 @@ fn TextInput(cx: Scope) -> Element {
 }
 
 #[inline_props]
-fn SimpleStats(cx: Scope, stats: UseRef<Stats>) -> Element {
+fn ConvertInput(cx: Scope, cfg: UseRef<Config>) -> Element {
+    let input_task = use_coroutine_handle::<InputAction>(&cx).expect("Could not get input task");
+    let simplified = cfg.read().simplified;
+    let script = if simplified { "简" } else { "繁" };
+
+    cx.render(rsx! {
+        " [Convert input "
+        button {
+            title: "Convert input text to the current script",
+            onclick: move |_| input_task.send(InputAction::Convert { simplified }),
+            "{script}"
+        }
+        "]"
+    })
+}
+
+#[inline_props]
+fn SimpleStats(
+    cx: Scope,
+    cfg: UseRef<Config>,
+    stats: UseRef<Stats>,
+    known: UseRef<KnownWords>,
+) -> Element {
+    let (levels, knownwords) = (cfg.read().levels, cfg.read().knownwords);
     let stats = stats.read();
     let unique_words = stats.hskwords.iter().fold(0, |acc, m| acc + m.len());
     let total_words = stats.hskcounts.iter().fold(0, |acc, c| acc + *c);
-    let avghsk = if unique_words == 0 {
+    // Only words with a level count towards the average.
+    let leveled = &stats.hskwords[..stats::MAX_HSK_LEVEL - 1];
+    let unique_leveled = leveled.iter().map(|m| m.len()).sum::<usize>();
+    let avghsk = if unique_leveled == 0 {
         0.0
     } else {
-        (stats
-            .hskwords
+        (leveled
             .iter()
             .enumerate()
             .map(|(idx, m)| (idx + 1) * m.len())
             .sum::<usize>() as f32)
-            / (unique_words as f32)
+            / (unique_leveled as f32)
     };
+    let (numbers, latin, punctuation, uncertain) = (
+        stats.numbers,
+        stats.latin,
+        stats.punctuation,
+        stats.uncertain,
+    );
+    // Lessons of word lists are numbered internally, show the nearest one.
+    let avglevel = match levels {
+        LevelSystem::Custom(_) => format!("lesson: {}", levels.short_label(avghsk.round() as u8)),
+        _ => format!("{}: {avghsk:.2}", levels.name()),
+    };
+    let script = stats.script.script();
+    let (script, trad_pct) = (script.label(), stats.script.traditional_ratio() * 100.0);
+    let total_unknown = stats.unknown.values().sum::<usize>();
+    let unique_unknown = stats.unknown.len();
+    let (known_total, known_unique) = known.read().coverage(&stats);
+    let known_pct = (known_total as f32) * 100.0 / (total_words.max(1) as f32);
     cx.render(rsx! {
         p {
             b {
                 "Words tot/uniq: {total_words}/{unique_words}"
-                (total_words > 0).then(|| rsx! { ", avg HSK: {avghsk:.2}" }),
+                (total_words > 0).then(|| rsx! { ", avg {avglevel}" }),
+                (knownwords && total_words > 0).then(|| rsx! {
+                    ", known: "
+                    span { title: "{known_unique} of {unique_words} unique words", "{known_pct:.0}%" }
+                }),
+                (total_words > 0).then(|| rsx! {
+                    ", script: "
+                    span { title: "{trad_pct:.0}% Traditional", "{script}" }
+                }),
             }
 
             small {
@@ -186,57 +579,672 @@ fn SimpleStats(cx: Scope, stats: UseRef<Stats>) -> Element {
                         let unique = m.len();
                         let pct = ((unique as f32) * 100f32) / (unique_words as f32);
                         rsx! {
-                            ", "
-                            if idx < 15 {
-                                rsx! {
-                                    "HSK"
-                                    b { "{idx}" }
-                                }
-                            } else {
-                                rsx! { "other" }
-                            }
-                            "("
-                            b { "{cnt}" }
-                            "/"
-                            b { "{unique}" }
-                            "/"
-                            b { "{pct:.0}" }
-                            "%)"
+                            LevelCount { levels: levels, level: idx, count: *cnt, unique: unique, pct: pct }
                         }
                     })
                 }
+
+            small {
+                (numbers > 0).then(|| rsx! { ", numbers: " b { "{numbers}" } }),
+                (latin > 0).then(|| rsx! { ", Latin: " b { "{latin}" } }),
+                (punctuation > 0).then(|| rsx! { ", punct.: " b { "{punctuation}" } }),
+                (uncertain > 0).then(|| rsx! { ", guessed: " b { "{uncertain}" } }),
+                (total_unknown > 0).then(|| rsx! {
+                    ", unknown: "
+                    b { "{total_unknown}" }
+                    "/"
+                    b { "{unique_unknown}" }
+                }),
+            }
         }
     })
 }
 
+/// Count, unique count and share of one level in the stats, levels past the
+/// last one are "other".
 #[inline_props]
-fn PrettyChinese(cx: Scope, cfg: UseRef<Config>, words: UseRef<Vec<Segment>>) -> Element {
+fn LevelCount(
+    cx: Scope,
+    levels: LevelSystem,
+    level: usize,
+    count: usize,
+    unique: usize,
+    pct: f32,
+) -> Element {
+    // Lesson labels already say what they are.
+    let name = match levels {
+        LevelSystem::Custom(_) => String::new(),
+        _ => levels.name(),
+    };
+    let label = levels.short_label(*level as u8);
+    cx.render(rsx! {
+        ", "
+        if *level < stats::MAX_HSK_LEVEL {
+            rsx! {
+                "{name}"
+                b { "{label}" }
+            }
+        } else {
+            rsx! { "other" }
+        }
+        "("
+        b { "{count}" }
+        "/"
+        b { "{unique}" }
+        "/"
+        b { "{pct:.0}" }
+        "%)"
+    })
+}
+
+/// Most characters listed as the rarest in the text.
+const MAX_RARE_CHARS: usize = 10;
+
+#[inline_props]
+fn CharStats(cx: Scope, cfg: UseRef<Config>, stats: UseRef<Stats>) -> Element {
+    let (simplified, levels) = (cfg.read().simplified, cfg.read().levels);
+    let stats = stats.read();
+    let (total, unique) = (stats.total_chars(), stats.unique_chars());
+    if total == 0 {
+        return None;
+    }
+    let show = |c: char| convert::convert(&c.to_string(), simplified);
+    let rarest = stats
+        .rarest_chars(MAX_RARE_CHARS)
+        .into_iter()
+        .map(|(c, _)| show(c))
+        .collect::<String>();
+    let mut uncovered = stats.uncovered.keys().copied().collect::<Vec<_>>();
+    uncovered.sort_unstable();
+    let uncovered = uncovered.into_iter().map(show).collect::<String>();
+
+    cx.render(rsx! {
+        p {
+            b { "Characters tot/uniq: {total}/{unique}" }
+            small {
+                stats.hskchars.iter().enumerate()
+                    .filter(|(_, cm)| !cm.is_empty())
+                    .map(|(idx, cm)| {
+                        let idx = idx + 1;
+                        let count = cm.values().sum::<usize>();
+                        let unique_level = cm.len();
+                        let pct = ((unique_level as f32) * 100f32) / (unique as f32);
+                        rsx! {
+                            LevelCount { levels: levels, level: idx, count: count, unique: unique_level, pct: pct }
+                        }
+                    })
+            }
+            br { }
+            small {
+                "Rarest: "
+                b { "{rarest}" }
+                (!uncovered.is_empty()).then(|| rsx! {
+                    ", not in any word: "
+                    b { "{uncovered}" }
+                }),
+            }
+        }
+    })
+}
+
+#[inline_props]
+fn PrettyChinese(
+    cx: Scope,
+    cfg: UseRef<Config>,
+    words: UseRef<Vec<Segment>>,
+    patterns: UseRef<Vec<PatternMatch>>,
+    findings: UseRef<Vec<Finding>>,
+    selected: UseRef<Option<Reference>>,
+    known: UseRef<KnownWords>,
+    revealed: UseRef<RevealedWords>,
+) -> Element {
+    let highlighted = if cfg.read().grammar {
+        patterns
+            .read()
+            .iter()
+            .flat_map(|m| m.segments.iter().copied())
+            .collect::<HashSet<_>>()
+    } else {
+        HashSet::default()
+    };
+    let findings = if cfg.read().particles {
+        findings.read().clone()
+    } else {
+        Vec::default()
+    };
     cx.render(rsx! {
         div {
-            words.read().iter().cloned().map(|word| {
-                rsx! { Chinese { cfg: cfg.clone(), word: word } }
+            words.read().iter().cloned().enumerate().map(|(idx, word)| {
+                let highlight = highlighted.contains(&idx);
+                let misuse = findings
+                    .iter()
+                    .find(|f| f.segment == idx)
+                    .map(|f| format!("Use {} instead of {}: {}", f.suggestion, f.found, f.reason))
+                    .unwrap_or_default();
+                rsx! {
+                    Chinese {
+                        cfg: cfg.clone(),
+                        selected: selected.clone(),
+                        known: known.clone(),
+                        revealed: revealed.clone(),
+                        word: word,
+                        highlight: highlight,
+                        misuse: misuse,
+                    }
+                }
             })
         }
     })
 }
 
+#[inline_props]
+fn RevealPanel(cx: Scope, cfg: UseRef<Config>, revealed: UseRef<RevealedWords>) -> Element {
+    let known_task = use_coroutine_handle::<KnownAction>(&cx).expect("Could not get known task");
+    let simplified = cfg.read().simplified;
+    if !cfg.read().revealhints {
+        return None;
+    }
+    let words = revealed.read().words().to_vec();
+    let count = words.len();
+
+    cx.render(rsx! {
+        details {
+            class: "grammar-panel",
+            summary { "Revealed hints: {count}" }
+            ul {
+                words.into_iter().map(|we| {
+                    let word = if simplified { &we.simplified } else { &we.traditional };
+                    let pinyin = &we.pinyin_marks;
+                    let gloss = words::short_gloss(we).unwrap_or_default();
+                    rsx! {
+                        li {
+                            b { "{word}" }
+                            " {pinyin}: {gloss}"
+                        }
+                    }
+                })
+            }
+            button {
+                title: "Mark the revealed words as learning in the known words",
+                onclick: move |_| known_task.send(KnownAction::Learn(revealed.read().to_learning())),
+                "Mark as learning"
+            }
+            button { onclick: move |_| revealed.set(RevealedWords::default()), "Reset" }
+        }
+    })
+}
+
+#[inline_props]
+fn GrammarPanel(
+    cx: Scope,
+    cfg: UseRef<Config>,
+    words: UseRef<Vec<Segment>>,
+    patterns: UseRef<Vec<PatternMatch>>,
+) -> Element {
+    let patterns = patterns.read();
+    if !cfg.read().grammar || patterns.is_empty() {
+        return None;
+    }
+    let simplified = cfg.read().simplified;
+    let words = words.read();
+    let mut found = Vec::<(&grammar::Pattern, Vec<String>)>::new();
+    for m in patterns.iter() {
+        let example = m
+            .segments
+            .iter()
+            .filter_map(|idx| words[*idx].as_chinese()?.first())
+            .map(|we| {
+                if simplified {
+                    we.simplified.as_str()
+                } else {
+                    we.traditional.as_str()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("…");
+        match found.iter_mut().find(|(p, _)| p.name == m.pattern().name) {
+            Some((_, examples)) => examples.push(example),
+            None => found.push((m.pattern(), vec![example])),
+        }
+    }
+
+    cx.render(rsx! {
+        div {
+            class: "grammar-panel",
+            h3 { "Grammar patterns:" }
+            ul {
+                found.into_iter().map(|(pattern, examples)| {
+                    let count = examples.len();
+                    let name = pattern.name;
+                    let explanation = pattern.explanation;
+                    let examples = examples.into_iter().take(3).collect::<Vec<_>>().join(", ");
+                    rsx! {
+                        li {
+                            b { "{name}" }
+                            " ({count}): {explanation} "
+                            small { "[{examples}]" }
+                        }
+                    }
+                })
+            }
+        }
+    })
+}
+
+#[inline_props]
+fn ParticlePanel(
+    cx: Scope,
+    cfg: UseRef<Config>,
+    words: UseRef<Vec<Segment>>,
+    findings: UseRef<Vec<Finding>>,
+) -> Element {
+    let findings = findings.read();
+    if !cfg.read().particles || findings.is_empty() {
+        return None;
+    }
+    let simplified = cfg.read().simplified;
+    let words = words.read();
+    let text = |idx: usize| {
+        words
+            .get(idx)
+            .and_then(|seg| seg.as_chinese()?.first())
+            .map(|we| {
+                if simplified {
+                    we.simplified.as_str()
+                } else {
+                    we.traditional.as_str()
+                }
+            })
+            .unwrap_or_default()
+    };
+
+    cx.render(rsx! {
+        div {
+            class: "grammar-panel",
+            h3 { "的/得/地 check:" }
+            ul {
+                findings.iter().map(|f| {
+                    let before = text(f.segment.wrapping_sub(1));
+                    let after = text(f.segment + 1);
+                    let (found, suggestion, reason) = (f.found, f.suggestion, f.reason);
+                    rsx! {
+                        li {
+                            "{before}"
+                            span { class: "misuse", "{found}" }
+                            "{after}: "
+                            b { "{found} → {suggestion}" }
+                            ", {reason}"
+                        }
+                    }
+                })
+            }
+        }
+    })
+}
+
+#[inline_props]
+fn MeasureWordPanel(cx: Scope, cfg: UseRef<Config>, words: UseRef<Vec<Segment>>) -> Element {
+    let simplified = cfg.read().simplified;
+    if !cfg.read().measurewords {
+        return None;
+    }
+    let words = words.read();
+    let mut nouns = Vec::<(&cd::WordEntry, Vec<measure::Classifier>, usize)>::new();
+    for seg in words.iter() {
+        let defs = match seg {
+            Segment::Chinese(w) if w.pos == Pos::Noun => &w.defs,
+            _ => continue,
+        };
+        let we = defs[0];
+        match nouns
+            .iter_mut()
+            .find(|(other, _, _)| other.word_id == we.word_id)
+        {
+            Some((_, _, count)) => *count += 1,
+            None => {
+                let classifiers = measure::word_classifiers(defs);
+                if !classifiers.is_empty() {
+                    nouns.push((we, classifiers, 1));
+                }
+            }
+        }
+    }
+    if nouns.is_empty() {
+        return None;
+    }
+
+    cx.render(rsx! {
+        div {
+            class: "grammar-panel",
+            h3 { "Measure words:" }
+            ul {
+                nouns.into_iter().map(|(we, classifiers, count)| {
+                    let word = if simplified { &we.simplified } else { &we.traditional };
+                    let pinyin = &we.pinyin_marks;
+                    let classifiers = measure::format_classifiers(&classifiers, simplified);
+                    rsx! {
+                        li {
+                            b { "{word}" }
+                            " {pinyin} ({count}): {classifiers}"
+                        }
+                    }
+                })
+            }
+        }
+    })
+}
+
+#[inline_props]
+fn WordDetail(
+    cx: Scope,
+    cfg: UseRef<Config>,
+    words: UseRef<Vec<Segment>>,
+    selected: UseRef<Option<Reference>>,
+) -> Element {
+    let current = selected.read().clone()?;
+    let simplified = cfg.read().simplified;
+    let word = current.text(simplified).to_owned();
+    let defs = current.entries();
+    let (simp, trad) = defs
+        .first()
+        .map(|we| (we.simplified.clone(), we.traditional.clone()))
+        .unwrap_or_else(|| (current.simplified.clone(), current.traditional.clone()));
+    let forms = if simp == trad {
+        "Same in Simplified and Traditional".to_owned()
+    } else {
+        format!("Simplified {simp}, Traditional {trad}")
+    };
+
+    let charinfo = match word.chars().collect::<Vec<_>>()[..] {
+        [c] => characters::describe(c),
+        _ => None,
+    };
+
+    // Each character with the reading it has in this word.
+    let syllables = defs
+        .first()
+        .map(|we| we.pinyin_numbers.split_whitespace().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let breakdown = if simp.chars().nth(1).is_some() {
+        simp.chars()
+            .zip(trad.chars())
+            .enumerate()
+            .map(|(idx, (s, t))| {
+                let r = Reference {
+                    traditional: t.to_string(),
+                    simplified: s.to_string(),
+                    pinyin: (syllables.len() == simp.chars().count())
+                        .then(|| syllables[idx].to_owned()),
+                };
+                let gloss = r
+                    .entries()
+                    .iter()
+                    .find_map(|we| words::short_gloss(we))
+                    .unwrap_or_default();
+                (r, gloss)
+            })
+            .collect::<Vec<_>>()
+    } else {
+        Vec::default()
+    };
+
+    let links = {
+        let currcfg = cfg.read();
+        let template = &currcfg.linktemplate;
+        if currcfg.linkchars && !breakdown.is_empty() {
+            breakdown
+                .iter()
+                .map(|(r, _)| {
+                    let pinyin = r.pinyin.clone().unwrap_or_default();
+                    let url = link_url(template, &r.simplified, &r.traditional, &pinyin);
+                    (r.text(simplified).to_owned(), url)
+                })
+                .collect::<Vec<_>>()
+        } else {
+            let pinyin = syllables.join(" ");
+            vec![(word.clone(), link_url(template, &simp, &trad, &pinyin))]
+        }
+    };
+    let linkname = LINK_NAMES[cfg.read().link_preset()];
+    let levels = cfg.read().levels;
+
+    // Other words in the text with any of the same characters.
+    let mut related = Vec::<Reference>::new();
+    for seg in words.read().iter() {
+        let we = match seg.as_chinese().and_then(|defs| defs.first()) {
+            Some(we) => we,
+            None => continue,
+        };
+        if we.simplified != simp
+            && we.simplified.chars().any(|c| simp.contains(c))
+            && !related.iter().any(|r| r.simplified == we.simplified)
+        {
+            related.push(Reference {
+                traditional: we.traditional.clone(),
+                simplified: we.simplified.clone(),
+                pinyin: None,
+            });
+        }
+    }
+
+    cx.render(rsx! {
+        div {
+            class: "detail-panel",
+            h3 {
+                "{word} "
+                button { title: "Close", onclick: move |_| selected.set(None), "×" }
+            }
+            small { "{forms}" }
+            charinfo.map(|info| rsx! { p { "{info}" } }),
+            defs.is_empty().then(|| rsx! { p { "Not found in dictionary." } }),
+            defs.into_iter().map(|we| {
+                let pinyin = &we.pinyin_marks;
+                let level = levels.level(we);
+                let label = levels.label(level);
+                let classifiers = measure::format_classifiers(&measure::classifiers(we), simplified);
+                rsx! {
+                    div {
+                        b { "{pinyin}" }
+                        (level > 0).then(|| rsx! { " ({label})" }),
+                        ol {
+                            we.english.iter()
+                                .filter(|sense| !measure::strip_classifiers(sense).is_empty())
+                                .map(|sense| rsx! {
+                                    li { Sense { cfg: cfg.clone(), selected: selected.clone(), sense: sense.clone() } }
+                                })
+                        }
+                        (!classifiers.is_empty()).then(|| rsx! { p { "Measure words: {classifiers}" } }),
+                    }
+                }
+            }),
+            (!breakdown.is_empty()).then(|| rsx! {
+                h4 { "Characters:" }
+                ul {
+                    breakdown.into_iter().map(|(r, gloss)| {
+                        let text = r.text(simplified).to_owned();
+                        let marks = r.pinyin_marks().unwrap_or_default();
+                        let info = text.chars().next().and_then(characters::describe);
+                        rsx! {
+                            li {
+                                a { class: "reflink", onclick: move |_| selected.set(Some(r.clone())), "{text}" }
+                                " {marks} {gloss}"
+                                info.map(|info| rsx! { br { } small { "{info}" } })
+                            }
+                        }
+                    })
+                }
+            }),
+            (!cfg.read().linktemplate.is_empty()).then(|| rsx! {
+                p {
+                    "{linkname}: "
+                    links.into_iter().map(|(label, url)| rsx! {
+                        a { href: "{url}", target: "_blank", "{label}" }
+                        " "
+                    })
+                }
+            }),
+            (!related.is_empty()).then(|| rsx! {
+                h4 { "In this text:" }
+                p {
+                    related.into_iter().map(|r| {
+                        let text = r.text(simplified).to_owned();
+                        rsx! {
+                            a { class: "reflink", onclick: move |_| selected.set(Some(r.clone())), "{text}" }
+                            " "
+                        }
+                    })
+                }
+            }),
+        }
+    })
+}
+
+/// Looks up words by Hanzi, pinyin or English, opening them in the detail
+/// view when clicked.
+#[inline_props]
+fn SearchPanel(
+    cx: Scope,
+    cfg: UseRef<Config>,
+    selected: UseRef<Option<Reference>>,
+    known: UseRef<KnownWords>,
+) -> Element {
+    // Words revealed here aren't from the text, so they stay out of the
+    // revealed words panel.
+    let revealed = use_ref(&cx, RevealedWords::default);
+    let query = use_state(&cx, String::new);
+    let mode = use_state(&cx, || 0usize);
+    // The last search, only looked up again when the query, the mode or the
+    // dictionary changes.
+    let cache = use_ref(&cx, || ((String::new(), 0usize, 0usize), Vec::new()));
+    let key = (query.get().clone(), *mode.get(), dictionary::generation());
+    if cache.read().0 != key {
+        let results = search::search(query.get(), SearchMode::from(*mode.get()));
+        *cache.write_silent() = (key, results);
+    }
+    let results = cache.read().1.clone();
+    let searched = !query.get().trim().is_empty();
+
+    cx.render(rsx! {
+        details {
+            class: "grammar-panel",
+            summary { "Dictionary search" }
+            input {
+                r#type: "search",
+                placeholder: "汉字, pinyin or English",
+                value: "{query}",
+                oninput: move |evt| query.set(evt.value.clone()),
+            }
+            " "
+            MultiOption {
+                label: "Search by",
+                options: SearchMode::OPTIONS,
+                current: *mode.get(),
+                onchange: |evt: FormEvent| mode.set(evt.data.value.parse::<usize>().unwrap_or(0)),
+            }
+            (searched && results.is_empty()).then(|| rsx! { p { "No results." } }),
+            ul {
+                results.into_iter().map(|we| {
+                    let gloss = words::short_gloss(we).unwrap_or_default();
+                    rsx! {
+                        li {
+                            Chinese {
+                                cfg: cfg.clone(),
+                                selected: selected.clone(),
+                                known: known.clone(),
+                                revealed: revealed.clone(),
+                                word: Segment::Chinese(Word::new(vec![we])),
+                                highlight: false,
+                                misuse: String::new(),
+                            }
+                            " {gloss}"
+                        }
+                    }
+                })
+            }
+        }
+    })
+}
+
+/// A definition sense with references to other entries that open them.
+#[inline_props]
+fn Sense(
+    cx: Scope,
+    cfg: UseRef<Config>,
+    selected: UseRef<Option<Reference>>,
+    sense: String,
+) -> Element {
+    let simplified = cfg.read().simplified;
+    let parts = parse_sense(sense);
+    cx.render(rsx! {
+        parts.into_iter().map(|part| match part {
+            SensePart::Text(text) => rsx! { "{text}" },
+            SensePart::Pinyin(pinyin) => {
+                let marks = ph::tone_marks(&pinyin);
+                rsx! { "{marks}" }
+            }
+            SensePart::Ref(r) => {
+                let text = r.text(simplified).to_owned();
+                let marks = r.pinyin_marks().map(|m| format!(" ({m})")).unwrap_or_default();
+                rsx! {
+                    a { class: "reflink", onclick: move |_| selected.set(Some(r.clone())), "{text}" }
+                    "{marks}"
+                }
+            }
+        })
+    })
+}
+
 #[inline_props]
 #[allow(unused_variables)]
 fn WordSpan<'a>(
     cx: Scope<'a>,
     cfg: UseRef<Config>,
     defs: Vec<&'static cd::WordEntry>,
+    uncertain: bool,
+    idiom: bool,
+    highlight: bool,
+    misuse: String,
+    pos: Pos,
+    familiarity: Familiarity,
     children: Element<'a>,
 ) -> Element {
     let cfg = cfg.read();
     let word = defs[0];
-    let hsk = if cfg.hsk { word.hsk } else { 99 };
+    let hsk = if cfg.hsk { cfg.levels.level(word) } else { 99 };
     let wordspacing = if cfg.wordspace { "" } else { "unspaced" };
+    let uncertain = if *uncertain { " uncertain" } else { "" };
+    let idiomclass = if *idiom { " idiom" } else { "" };
+    let grammarclass = if *highlight { " grammar" } else { "" };
+    let misuseclass = if misuse.is_empty() { "" } else { " misuse" };
+    let knownclass = match (cfg.knownwords, familiarity) {
+        (false, _) | (_, Familiarity::Known) => "",
+        (true, Familiarity::Learning) => " learning",
+        (true, Familiarity::Unknown) => " unfamiliar",
+    };
+    let posclass = if cfg.poscolor {
+        format!(" pos-{}", pos.name())
+    } else {
+        String::default()
+    };
+    // Lessons of word lists get colors spread around the color wheel, words
+    // that aren't in the list are highlighted.
+    let (levelstyle, offlist) = match cfg.levels {
+        LevelSystem::Custom(_) if cfg.hsk && hsk == 0 => (String::default(), " offlist"),
+        LevelSystem::Custom(_) if cfg.hsk => {
+            let hue = 300 * (hsk as u32 - 1) / cfg.levels.level_count().max(1) as u32;
+            (
+                format!("border-bottom: 2px dotted hsl({hue}, 65%, 45%);"),
+                "",
+            )
+        }
+        _ => (String::default(), ""),
+    };
 
     if !cfg.tooltips {
         return cx.render(rsx! {
             span {
-                class: "word{wordspacing} hsk{hsk}",
+                class: "word{wordspacing} hsk{hsk}{uncertain}{idiomclass}{grammarclass}{misuseclass}{posclass}{offlist}{knownclass}",
+            style: "{levelstyle}",
                 &cx.props.children
             }
         });
@@ -249,47 +1257,250 @@ fn WordSpan<'a>(
             let defs = thisreading
                 .english
                 .iter()
+                .map(|d| render_sense(d, cfg.simplified))
+                .filter(|d| !d.is_empty())
                 .enumerate()
                 .map(|(idx, d)| format!("  {}. {d}\n", idx + 1))
                 .collect::<String>();
+            let classifiers = measure::classifiers(thisreading);
+            let defs = if classifiers.is_empty() {
+                defs
+            } else {
+                format!(
+                    "{defs}  Measure words: {}\n",
+                    measure::format_classifiers(&classifiers, cfg.simplified)
+                )
+            };
             format!(
                 "({}) {} {} [trad. {}]{}:\n{defs}\n",
                 idx + 1,
                 thisreading.simplified,
                 thisreading.pinyin_marks,
                 thisreading.traditional,
-                if idx == 0 && thisreading.hsk > 0 {
-                    format!(" (HSK {})", thisreading.hsk)
-                } else {
-                    String::default()
+                match cfg.levels.level(thisreading) {
+                    level if idx == 0 && level > 0 => format!(" ({})", cfg.levels.label(level)),
+                    _ => String::default(),
                 }
             )
         })
         .collect::<String>();
+    let variants = word
+        .simplified
+        .chars()
+        .filter_map(|c| {
+            let alts = convert::alternatives(c, false);
+            (alts.len() > 1).then(|| format!("{c}: {}\n", alts.join(" ")))
+        })
+        .collect::<String>();
+    let tooltip = if variants.is_empty() {
+        tooltip
+    } else {
+        format!("{tooltip}Traditional forms:\n{variants}")
+    };
+    let written = if cfg.simplified {
+        &word.simplified
+    } else {
+        &word.traditional
+    };
+    let chars = written
+        .chars()
+        .filter_map(|c| characters::describe(c).map(|d| d + "\n"))
+        .collect::<String>();
+    let tooltip = if chars.is_empty() {
+        tooltip
+    } else {
+        format!("{tooltip}Characters:\n{chars}")
+    };
+    let tooltip = if cfg.poscolor || cfg.shows_hint(Hint::Pos) {
+        format!("[Word class (guessed): {}]\n\n{tooltip}", pos.name())
+    } else {
+        tooltip
+    };
+    let tooltip = if *idiom {
+        let chars = if cfg.simplified {
+            &word.simplified
+        } else {
+            &word.traditional
+        };
+        format!(
+            "[Idiom] Literally: {}\n\n{tooltip}",
+            words::literal_gloss(chars)
+        )
+    } else {
+        tooltip
+    };
+    let tooltip = if uncertain.is_empty() {
+        tooltip
+    } else {
+        format!("[Uncertain: segmentation guessed from unknown text]\n\n{tooltip}")
+    };
+    let tooltip = if misuse.is_empty() {
+        tooltip
+    } else {
+        format!("[{misuse}]\n\n{tooltip}")
+    };
 
     cx.render(rsx! {
         span {
             title: "{tooltip}",
-            class: "word{wordspacing} hsk{hsk}",
+            class: "word{wordspacing} hsk{hsk}{uncertain}{idiomclass}{grammarclass}{misuseclass}{posclass}{offlist}{knownclass}",
+            style: "{levelstyle}",
             &cx.props.children
         }
     })
 }
 
 #[inline_props]
-fn Chinese(cx: Scope, cfg: UseRef<Config>, word: Segment) -> Element {
+fn NumberSpan(cx: Scope, cfg: UseRef<Config>, num: Number) -> Element {
+    let currcfg = cfg.read();
+    let text = convert::convert(&num.text, currcfg.simplified);
+    let wordspacing = if currcfg.wordspace { "" } else { "unspaced" };
+    let hints = num
+        .readings
+        .iter()
+        .map(|r| {
+            let tone = if currcfg.tonecolor { r.tone } else { 99 };
+            let phon = ph::Syllable::from_pinyin(r.pinyin).unwrap_or(ph::Syllable {
+                init: ph::Initial::Q,
+                fin: ph::Final::A,
+            });
+            let hint1 = generate_hint(currcfg.hint, r.hanzi, &phon, r.tone, r.marks);
+            let hint2 = generate_hint(currcfg.hint2, r.hanzi, &phon, r.tone, r.marks);
+            (
+                hint1.map(|h| h.to_string()),
+                hint2.map(|h| h.to_string()),
+                tone,
+            )
+        })
+        .collect::<Vec<_>>();
+    let (pos1, pos2) = (currcfg.hintpos.class(), currcfg.hint2pos.class());
+    let tooltip = if currcfg.tooltips {
+        let hanzi = convert::convert(&num.hanzi, currcfg.simplified);
+        let marks = num
+            .readings
+            .iter()
+            .map(|r| r.marks)
+            .collect::<Vec<_>>()
+            .join(" ");
+        match &num.value {
+            Some(value) => format!("{hanzi} {marks}\n= {value}"),
+            None => format!("{hanzi} {marks}"),
+        }
+    } else {
+        String::default()
+    };
+
+    // Chinese numerals and years line up with their syllables, other
+    // numbers get the whole reading at once.
+    if num.aligned && text.chars().count() == hints.len() {
+        return cx.render(rsx! {
+            span {
+                title: "{tooltip}",
+                class: "word{wordspacing} number",
+                ruby {
+                    text.chars().zip(hints.into_iter()).map(|(c, (hint1, hint2, tone))| rsx! {
+                        ruby {
+                            class: "{pos2}",
+                            ruby {
+                                class: "{pos1}",
+                                span { class: "tone{tone}", "{c}" }
+                                hint1.map(|hint| rsx! {
+                                    rt { class: "tone{tone}", "{hint}" }
+                                })
+                            }
+                            hint2.map(|hint| rsx! {
+                                rt { class: "tone{tone}", "{hint}" }
+                            })
+                        }
+                    })
+                }
+            }
+        });
+    }
+    let (show1, show2) = (
+        hints.iter().any(|(hint, _, _)| hint.is_some()),
+        hints.iter().any(|(_, hint, _)| hint.is_some()),
+    );
+    let hints = &hints;
+    cx.render(rsx! {
+        span {
+            title: "{tooltip}",
+            class: "word{wordspacing} number",
+            ruby {
+                class: "{pos2}",
+                ruby {
+                    class: "{pos1}",
+                    span { class: "tone5", "{text}" }
+                    show1.then(|| rsx! {
+                        rt {
+                            hints.iter().filter_map(|(hint, _, tone)| {
+                                let hint = hint.as_ref()?;
+                                Some(rsx! { span { class: "tone{tone}", "{hint} " } })
+                            })
+                        }
+                    })
+                }
+                show2.then(|| rsx! {
+                    rt {
+                        hints.iter().filter_map(|(_, hint, tone)| {
+                            let hint = hint.as_ref()?;
+                            Some(rsx! { span { class: "tone{tone}", "{hint} " } })
+                        })
+                    }
+                })
+            }
+        }
+    })
+}
+
+#[inline_props]
+fn Chinese(
+    cx: Scope,
+    cfg: UseRef<Config>,
+    selected: UseRef<Option<Reference>>,
+    known: UseRef<KnownWords>,
+    revealed: UseRef<RevealedWords>,
+    word: Segment,
+    highlight: bool,
+    misuse: String,
+) -> Element {
+    let known_task = use_coroutine_handle::<KnownAction>(&cx).expect("Could not get known task");
     let word = word.clone();
     let currcfg = cfg.read();
-    let defs = match word {
+    let chword = match word {
         Segment::Break => return cx.render(rsx! { br { } }),
-        Segment::Plain(plain) => {
+        Segment::Chinese(ref chword) => chword,
+        Segment::Number(num) => {
             return cx.render(rsx! {
-                span { class: "tone5 plain", "{plain}" }
+                NumberSpan { cfg: cx.props.cfg.clone(), num: num }
             })
         }
-        Segment::Chinese(ref defs) => defs,
+        ref other => {
+            let class = match other {
+                Segment::Punctuation(_) => "punct",
+                Segment::Latin(_) => "latin",
+                Segment::Whitespace(_) => "space",
+                Segment::UnknownHan(_) => "unknown",
+                _ => "",
+            };
+            let title = if currcfg.tooltips && class == "unknown" {
+                "Not found in dictionary"
+            } else {
+                ""
+            };
+            let text = match other {
+                Segment::UnknownHan(txt) => convert::convert(txt, currcfg.simplified),
+                _ => other.as_text().unwrap_or_default().to_owned(),
+            };
+            return cx.render(rsx! {
+                span { class: "tone5 plain {class}", title: "{title}", "{text}" }
+            });
+        }
     };
-    let defs = defs.clone();
+    let uncertain = chword.uncertain;
+    let idiom = chword.idiom;
+    let pos = chword.pos;
+    let defs = chword.defs.clone();
     let thisword = defs[0];
 
     let cchars = if currcfg.simplified {
@@ -304,7 +1515,46 @@ fn Chinese(cx: Scope, cfg: UseRef<Config>, word: Segment) -> Element {
             .zip(thisword.pinyin_marks.split_whitespace())
             .zip(thisword.tone_marks.clone()),
     );
+    let familiarity = if currcfg.knownwords {
+        known.read().get(&thisword.simplified)
+    } else {
+        Familiarity::Unknown
+    };
+    let hide_hints =
+        currcfg.knownwords && familiarity == Familiarity::Known || !currcfg.hints_word(thisword);
+    let markknown = currcfg.markknown;
+    // Only words that had a hint to hide count as revealed.
+    let concealed = currcfg.revealhints
+        && !hide_hints
+        && !currcfg.hint_layers().is_empty()
+        && !revealed.read().contains(thisword);
+    let conceal = if concealed { " concealed" } else { "" };
+    // Words can be tabbed to for revealing their hints.
+    let tabindex = if currcfg.revealhints { "0" } else { "-1" };
+    let reveal = move || {
+        if concealed {
+            revealed.write().reveal(thisword);
+        }
+    };
     let tone_color = currcfg.tonecolor;
+    let level = currcfg.levels.short_label(currcfg.levels.level(thisword));
+    let (pos1, pos2) = (currcfg.hintpos.class(), currcfg.hint2pos.class());
+    // Hints about the whole word go around all of its characters, the
+    // others around each character.
+    let word_layer = |hint: Hint| {
+        let simplified = currcfg.simplified;
+        (!hide_hints)
+            .then(|| words::word_hint(hint, chword, &level, simplified, currcfg.glosslen))
+            .flatten()
+    };
+    let (word1, word2) = (word_layer(currcfg.hint), word_layer(currcfg.hint2));
+    let layer_hint = |hint: Hint, c: char, phon: &ph::Syllable, tone: u8, pinyintm| {
+        if hide_hints {
+            None
+        } else {
+            generate_hint(hint, c, phon, tone, pinyintm).map(|h| h.to_string())
+        }
+    };
     let hints = pwords
         .into_iter()
         .map(|(c, ((pinyin, pinyintm), mut tone))| {
@@ -313,42 +1563,71 @@ fn Chinese(cx: Scope, cfg: UseRef<Config>, word: Segment) -> Element {
             if !tone_color {
                 tone = 99
             }
-            let linkchars = if currcfg.simplified {
-                &thisword.simplified
-            } else {
-                &thisword.traditional
-            };
             let phon = ph::Syllable::from_pinyin(pinyin).unwrap_or(ph::Syllable {
                 init: ph::Initial::Q,
                 fin: ph::Final::A,
             });
-            let maybehint_top = generate_hint(currcfg.hint, &phon, tone, thisword.hsk, pinyintm);
-            (
-                c,
-                linkchars.to_owned(),
-                maybehint_top.map(|h| h.to_string()),
-                tone,
-            )
+            let hint1 = layer_hint(currcfg.hint, c, &phon, tone, pinyintm);
+            let hint2 = layer_hint(currcfg.hint2, c, &phon, tone, pinyintm);
+            (c, hint1, hint2, tone)
         });
+    let reference = Reference {
+        traditional: thisword.traditional.clone(),
+        simplified: thisword.simplified.clone(),
+        pinyin: None,
+    };
     let output = rsx! {
         WordSpan {
             cfg: cx.props.cfg.clone(),
             defs: defs,
+            uncertain: uncertain,
+            idiom: idiom,
+            highlight: *highlight,
+            misuse: misuse.clone(),
+            pos: pos,
+            familiarity: familiarity,
             ruby {
-                hints.map(|(c, linkchars, maybehint_top, tone)| {
-                    rsx! {
-                        ruby {
-                            a {
-                                href: "https://www.mdbg.net/chinese/dictionary?page=worddict&wdrst=0&wdqb={linkchars}",
-                                class: "wordlink tone{tone}",
-                                target: "_blank",
-                                "{c}"
+                class: "{pos2}",
+                tabindex: "{tabindex}",
+                onmouseenter: move |_| reveal(),
+                onfocus: move |_| reveal(),
+                ruby {
+                    class: "{pos1}",
+                    hints.map(|(c, hint1, hint2, tone)| {
+                        let reference = reference.clone();
+                        // The second layer wraps the first as double ruby.
+                        rsx! {
+                            ruby {
+                                class: "{pos2}",
+                                ruby {
+                                    class: "{pos1}",
+                                    a {
+                                        class: "wordlink tone{tone}",
+                                        onclick: move |_| if markknown {
+                                            known_task.send(KnownAction::Cycle(reference.simplified.clone()));
+                                        } else if concealed {
+                                            reveal();
+                                        } else {
+                                            selected.set(Some(reference.clone()));
+                                        },
+                                        "{c}"
+                                    }
+                                    hint1.map(|hint| rsx! {
+                                        rt { class: "tone{tone}{conceal}", "{hint}" }
+                                    })
+                                }
+                                hint2.map(|hint| rsx! {
+                                    rt { class: "tone{tone}{conceal}", "{hint}" }
+                                })
                             }
-                            maybehint_top.map(|hint| rsx! {
-                                rt { class: "tone{tone}", "{hint}" }
-                            })
                         }
-                    }
+                    })
+                    word1.map(|hint| rsx! {
+                        rt { class: "word-hint{conceal}", "{hint}" }
+                    })
+                }
+                word2.map(|hint| rsx! {
+                    rt { class: "word-hint{conceal}", "{hint}" }
                 })
             }
         }
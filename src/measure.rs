@@ -0,0 +1,135 @@
+use std::borrow::Cow;
+
+use chinese_dictionary as cd;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::phonetic as ph;
+
+/// A measure word used with a noun.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Classifier {
+    pub simplified: String,
+    pub traditional: String,
+    /// Pinyin with tone marks.
+    pub pinyin: String,
+}
+
+impl Classifier {
+    pub fn text(&self, simplified: bool) -> &str {
+        if simplified {
+            &self.simplified
+        } else {
+            &self.traditional
+        }
+    }
+}
+
+static CL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\(?CL:([^;)]*)\)?").expect("Internal error: Could not compile regex")
+});
+
+/// Parses classifiers written the CC-CEDICT way, like
+/// `CL:個|个[ge4],條|条[tiao2]`, from a sense.
+pub fn parse_classifiers(sense: &str) -> Vec<Classifier> {
+    static ITEM: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^\s*([^|\[\s]+)(?:\|([^\[\s]+))?\[([^\]]+)\]")
+            .expect("Internal error: Could not compile regex")
+    });
+    CL_REGEX
+        .captures_iter(sense)
+        .flat_map(|cap| {
+            cap[1]
+                .split(',')
+                .filter_map(|item| {
+                    let item = ITEM.captures(item)?;
+                    let traditional = item[1].to_owned();
+                    Some(Classifier {
+                        simplified: item
+                            .get(2)
+                            .map_or_else(|| traditional.clone(), |m| m.as_str().to_owned()),
+                        traditional,
+                        pinyin: ph::tone_marks(&item[3]),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Removes classifier lists from a sense, they're shown separately.
+pub fn strip_classifiers(sense: &str) -> Cow<'_, str> {
+    if sense.contains("CL:") {
+        Cow::from(CL_REGEX.replace_all(sense, "").trim().to_owned())
+    } else {
+        Cow::from(sense)
+    }
+}
+
+/// The classifiers of a definition, both the ones the dictionary already
+/// parsed and any still embedded in its senses.
+pub fn classifiers(we: &cd::WordEntry) -> Vec<Classifier> {
+    let mut result = we
+        .measure_words
+        .iter()
+        .map(|mw| Classifier {
+            simplified: mw.simplified.clone(),
+            traditional: mw.traditional.clone(),
+            pinyin: mw.pinyin_marks.clone(),
+        })
+        .collect::<Vec<_>>();
+    we.english
+        .iter()
+        .flat_map(|sense| parse_classifiers(sense))
+        .for_each(|cl| {
+            if !result.iter().any(|other| other.simplified == cl.simplified) {
+                result.push(cl)
+            }
+        });
+    result
+}
+
+/// Classifiers of all the definitions of a word, without duplicates.
+pub fn word_classifiers(defs: &[&cd::WordEntry]) -> Vec<Classifier> {
+    let mut result = Vec::<Classifier>::new();
+    defs.iter().flat_map(|we| classifiers(we)).for_each(|cl| {
+        if !result.iter().any(|other| other.simplified == cl.simplified) {
+            result.push(cl)
+        }
+    });
+    result
+}
+
+/// Lists classifiers like 本 běn, 册 cè in the given script.
+pub fn format_classifiers(cls: &[Classifier], simplified: bool) -> String {
+    cls.iter()
+        .map(|cl| format!("{} {}", cl.text(simplified), cl.pinyin))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_classifiers() {
+        let cls = parse_classifiers("CL:個|个[ge4],條|条[tiao2]");
+        assert_eq!(cls.len(), 2);
+        assert_eq!(cls[0].traditional, "個");
+        assert_eq!(cls[0].simplified, "个");
+        assert_eq!(cls[0].pinyin, "gè");
+        assert_eq!(cls[1].text(false), "條");
+        let cls = parse_classifiers("cat (CL:隻|只[zhi1])");
+        assert_eq!(format_classifiers(&cls, true), "只 zhī");
+        assert_eq!(parse_classifiers("road (CL:道[dao4])")[0].simplified, "道");
+        assert_eq!(strip_classifiers("cat (CL:隻|只[zhi1])"), "cat");
+        assert_eq!(strip_classifiers("CL:個|个[ge4]"), "");
+    }
+
+    #[test]
+    pub fn test_classifiers() {
+        let cls = word_classifiers(&cd::query_by_simplified("书"));
+        assert_eq!(format_classifiers(&cls, false), "本 běn, 冊 cè, 部 bù");
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{pos::Pos, words::Segment};
+
+/// A 的, 得 or 地 that probably should be one of the others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Index of the particle's segment.
+    pub segment: usize,
+    pub found: &'static str,
+    pub suggestion: &'static str,
+    pub reason: &'static str,
+}
+
+fn particle(seg: &Segment) -> Option<&'static str> {
+    let we = seg.as_chinese()?.first()?;
+    ["的", "得", "地"].into_iter().find(|p| we.simplified == *p)
+}
+
+/// Index of the nearest word in direction `step`, skipping spaces. Anything
+/// else in between, like punctuation, means the words aren't related.
+fn neighbour(segments: &[Segment], idx: usize, step: isize) -> Option<usize> {
+    let mut idx = idx as isize + step;
+    while idx >= 0 && (idx as usize) < segments.len() {
+        match &segments[idx as usize] {
+            Segment::Whitespace(_) => idx += step,
+            _ => return Some(idx as usize),
+        }
+    }
+    None
+}
+
+fn ends_clause(segments: &[Segment], idx: Option<usize>) -> bool {
+    matches!(
+        idx.map(|idx| &segments[idx]),
+        None | Some(Segment::Punctuation(_)) | Some(Segment::Break)
+    )
+}
+
+/// Looks for 的/得/地 whose neighbours suggest a different particle.
+pub fn check_particles(segments: &[Segment]) -> Vec<Finding> {
+    segments
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, seg)| {
+            let found = particle(seg)?;
+            let prev = segments[neighbour(segments, idx, -1)?].pos()?;
+            let next_idx = neighbour(segments, idx, 1)?;
+            let next = segments[next_idx].pos()?;
+            let next_ends = ends_clause(segments, neighbour(segments, next_idx, 1));
+            let (suggestion, reason) = suggest(found, prev, next, next_ends)?;
+            Some(Finding {
+                segment: idx,
+                found,
+                suggestion,
+                reason,
+            })
+        })
+        .collect()
+}
+
+fn suggest(
+    found: &str,
+    prev: Pos,
+    next: Pos,
+    next_ends: bool,
+) -> Option<(&'static str, &'static str)> {
+    use Pos::*;
+
+    const COMPLEMENT: &str = "a complement of degree or result after a verb takes 得";
+    const ADVERBIAL: &str = "a modifier before a verb takes 地";
+    const ATTRIBUTE: &str = "a modifier before a noun takes 的";
+
+    match (found, prev, next) {
+        ("的" | "地", Verb | Adjective, Adverb) => Some(("得", COMPLEMENT)),
+        // 的 is fine here, like 他说的对 "what he said is right".
+        ("地", Verb, Adjective) if next_ends => Some(("得", COMPLEMENT)),
+        ("的", Adjective | Adverb, Verb) | ("得", Adverb, Verb) => Some(("地", ADVERBIAL)),
+        ("地" | "得", Adjective | Adverb, Noun) => Some(("的", ATTRIBUTE)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{levels::LevelSystem, words::make_words};
+
+    fn check(s: &str) -> Vec<(&'static str, &'static str)> {
+        let (segs, _) = make_words(s, false, LevelSystem::Hsk2);
+        check_particles(&segs)
+            .into_iter()
+            .map(|f| (f.found, f.suggestion))
+            .collect()
+    }
+
+    #[test]
+    pub fn test_particles() {
+        assert_eq!(check("他跑的很快。"), vec![("的", "得")]);
+        assert_eq!(check("他跑地快。"), vec![("地", "得")]);
+        assert!(check("他说的对。").is_empty());
+        assert_eq!(check("她高兴的说。"), vec![("的", "地")]);
+        assert_eq!(check("慢慢得走"), vec![("得", "地")]);
+        assert_eq!(check("美丽地花"), vec![("地", "的")]);
+        assert!(check("他跑得快。她高兴地说: 漂亮的衣服。我得走了").is_empty());
+    }
+}
@@ -632,6 +632,61 @@ impl Syllable {
     }
 }
 
+/// Vowels that can carry a tone mark, with their marked forms for tones 1-4.
+const TONE_MARKS: &[(char, [char; 4])] = &[
+    ('a', ['ā', 'á', 'ǎ', 'à']),
+    ('e', ['ē', 'é', 'ě', 'è']),
+    ('i', ['ī', 'í', 'ǐ', 'ì']),
+    ('o', ['ō', 'ó', 'ǒ', 'ò']),
+    ('u', ['ū', 'ú', 'ǔ', 'ù']),
+    ('ü', ['ǖ', 'ǘ', 'ǚ', 'ǜ']),
+    ('A', ['Ā', 'Á', 'Ǎ', 'À']),
+    ('E', ['Ē', 'É', 'Ě', 'È']),
+    ('O', ['Ō', 'Ó', 'Ǒ', 'Ò']),
+];
+
+/// Converts pinyin with tone numbers like "zhong1 guo2" to tone marks like
+/// "zhōng guó". Anything that isn't a numbered syllable is left alone.
+pub fn tone_marks(s: &str) -> String {
+    s.split(' ')
+        .map(syllable_tone_marks)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn syllable_tone_marks(syl: &str) -> String {
+    let syl = syl.replace("u:", "ü").replace("U:", "Ü").replace('v', "ü");
+    let tone = match syl.chars().last().and_then(|c| c.to_digit(10)) {
+        Some(tone) => tone as usize,
+        None => return syl,
+    };
+    let body = &syl[..syl.len() - 1];
+    if !(1..=4).contains(&tone) {
+        return body.to_owned();
+    }
+    let lower = body.to_lowercase();
+    // The mark goes on a or e, the o of ou, otherwise the last vowel.
+    let pos = lower
+        .find('a')
+        .or_else(|| lower.find('e'))
+        .or_else(|| lower.find("ou"))
+        .or_else(|| lower.rfind(['i', 'o', 'u', 'ü']));
+    let pos = match pos {
+        Some(pos) => pos,
+        None => return body.to_owned(),
+    };
+    let vowel = body[pos..].chars().next().unwrap_or_default();
+    match TONE_MARKS.iter().find(|(c, _)| *c == vowel) {
+        Some((_, marks)) => format!(
+            "{}{}{}",
+            &body[..pos],
+            marks[tone - 1],
+            &body[pos + vowel.len_utf8()..]
+        ),
+        None => body.to_owned(),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -673,6 +728,14 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn test_tone_marks() {
+        assert_eq!(tone_marks("zhong1 guo2"), "zhōng guó");
+        assert_eq!(tone_marks("nu:3 er2"), "nǚ ér");
+        assert_eq!(tone_marks("gou3 liu4 xiong2"), "gǒu liù xióng");
+        assert_eq!(tone_marks("Shu1 jing1 ma5"), "Shū jīng ma");
+    }
+
     #[test]
     // FIXME: Only verifies that parsing succeeds.
     pub fn test_pinyin_words() {
@@ -0,0 +1,427 @@
+use chinese_dictionary as cd;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{measure, words::Segment};
+
+/// Part of speech, guessed since the dictionary doesn't record them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pos {
+    Noun,
+    Verb,
+    Adjective,
+    Adverb,
+    Pronoun,
+    Numeral,
+    Measure,
+    Preposition,
+    Conjunction,
+    Particle,
+    Interjection,
+    Other,
+}
+
+impl Pos {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pos::Noun => "noun",
+            Pos::Verb => "verb",
+            Pos::Adjective => "adjective",
+            Pos::Adverb => "adverb",
+            Pos::Pronoun => "pronoun",
+            Pos::Numeral => "numeral",
+            Pos::Measure => "measure",
+            Pos::Preposition => "preposition",
+            Pos::Conjunction => "conjunction",
+            Pos::Particle => "particle",
+            Pos::Interjection => "interjection",
+            Pos::Other => "other",
+        }
+    }
+
+    pub fn abbr(&self) -> &'static str {
+        match self {
+            Pos::Noun => "n.",
+            Pos::Verb => "v.",
+            Pos::Adjective => "adj.",
+            Pos::Adverb => "adv.",
+            Pos::Pronoun => "pron.",
+            Pos::Numeral => "num.",
+            Pos::Measure => "m.",
+            Pos::Preposition => "prep.",
+            Pos::Conjunction => "conj.",
+            Pos::Particle => "part.",
+            Pos::Interjection => "int.",
+            Pos::Other => "?",
+        }
+    }
+}
+
+/// Function words whose senses don't reliably give away their role.
+static CLOSED_CLASSES: &[(Pos, &[&str])] = &[
+    (
+        Pos::Numeral,
+        &[
+            "零", "一", "二", "两", "三", "四", "五", "六", "七", "八", "九", "十", "百", "千",
+            "万", "亿", "半",
+        ],
+    ),
+    (
+        Pos::Pronoun,
+        &[
+            "我",
+            "你",
+            "您",
+            "他",
+            "她",
+            "它",
+            "我们",
+            "你们",
+            "他们",
+            "她们",
+            "它们",
+            "咱们",
+            "咱",
+            "自己",
+            "大家",
+            "别人",
+            "人家",
+            "这",
+            "那",
+            "这个",
+            "那个",
+            "这些",
+            "那些",
+            "这里",
+            "那里",
+            "这儿",
+            "那儿",
+            "什么",
+            "谁",
+            "哪",
+            "哪个",
+            "哪里",
+            "哪儿",
+            "怎么",
+            "怎么样",
+            "为什么",
+            "多少",
+            "几",
+            "每",
+            "某",
+        ],
+    ),
+    (
+        Pos::Particle,
+        &[
+            "的", "得", "地", "了", "着", "过", "吗", "呢", "吧", "啊", "呀", "嘛", "啦", "么",
+            "之",
+        ],
+    ),
+    (
+        Pos::Adverb,
+        &[
+            "很",
+            "非常",
+            "太",
+            "真",
+            "特别",
+            "十分",
+            "挺",
+            "更",
+            "最",
+            "比较",
+            "相当",
+            "极",
+            "越来越",
+            "不",
+            "没",
+            "也",
+            "都",
+            "还",
+            "就",
+            "才",
+            "又",
+            "再",
+            "已经",
+            "正在",
+            "刚",
+            "刚才",
+            "一直",
+            "常常",
+            "经常",
+            "总是",
+            "马上",
+            "一起",
+            "只",
+        ],
+    ),
+    (
+        Pos::Preposition,
+        &[
+            "在", "从", "对", "给", "向", "往", "跟", "把", "被", "比", "离", "为", "为了", "关于",
+            "除了", "按照", "通过", "根据", "对于", "由", "自从", "朝",
+        ],
+    ),
+    (
+        Pos::Conjunction,
+        &[
+            "和", "与", "但是", "可是", "但", "而且", "而", "因为", "所以", "如果", "虽然", "或者",
+            "还是", "然后", "不过", "并且", "要是", "即使", "只要", "只有", "不但", "不仅", "因此",
+            "于是", "否则", "既然", "尽管",
+        ],
+    ),
+];
+
+const ADJECTIVES: &[&str] = &[
+    "happy",
+    "glad",
+    "pretty",
+    "quick",
+    "fast",
+    "rapid",
+    "slow",
+    "big",
+    "large",
+    "small",
+    "little",
+    "good",
+    "bad",
+    "new",
+    "old",
+    "red",
+    "high",
+    "tall",
+    "low",
+    "long",
+    "short",
+    "hot",
+    "cold",
+    "warm",
+    "cool",
+    "easy",
+    "difficult",
+    "hard",
+    "clean",
+    "dirty",
+    "busy",
+    "tired",
+    "sad",
+    "angry",
+    "quiet",
+    "loud",
+    "many",
+    "much",
+    "few",
+    "near",
+    "far",
+    "early",
+    "late",
+    "right",
+    "correct",
+    "wrong",
+    "true",
+    "clear",
+    "bright",
+    "dark",
+    "heavy",
+    "light",
+    "young",
+    "cheap",
+    "expensive",
+    "interesting",
+    "boring",
+    "excited",
+    "satisfied",
+];
+
+const ADJECTIVE_SUFFIXES: &[&str] = &["ful", "ous", "ive", "able", "ible", "ic", "ish", "less"];
+
+const ADVERBS: &[&str] = &[
+    "already", "also", "too", "very", "all", "only", "just", "still", "again", "never", "always",
+    "often", "even", "soon", "together", "really", "quite", "almost", "not",
+];
+
+const PREPOSITIONS: &[&str] = &[
+    "at", "in", "on", "from", "with", "towards", "via", "through",
+];
+
+/// Senses that say nothing about how the word is used.
+static SKIP: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\s*(?:surname|variant of|old variant|unofficial variant|used in|see |abbr\. for|\(bound form\)|also pr\.)")
+        .expect("Internal error: Could not compile regex")
+});
+
+/// Guesses the part of speech a single English sense implies.
+fn sense_pos(sense: &str) -> Option<Pos> {
+    static PARENS: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"\([^)]*\)|\[[^]]*\]").expect("Internal error: Could not compile regex")
+    });
+    let lower = sense.trim().to_lowercase();
+    if lower.contains("particle") {
+        return Some(Pos::Particle);
+    }
+    if lower.contains("classifier for") {
+        return Some(Pos::Measure);
+    }
+    if lower.starts_with("interjection") || lower.starts_with("(onom.)") {
+        return Some(Pos::Interjection);
+    }
+    if lower.starts_with("(to be)") {
+        return Some(Pos::Verb);
+    }
+    let gloss = PARENS.replace_all(&lower, "");
+    let gloss = gloss.trim().trim_end_matches(['?', '!']);
+    let single = !gloss.contains(' ');
+    if gloss.is_empty() {
+        None
+    } else if gloss.starts_with("to ") {
+        Some(Pos::Verb)
+    } else if ADVERBS.contains(&gloss) || (single && gloss.ends_with("ly")) {
+        Some(Pos::Adverb)
+    } else if ADJECTIVES.contains(&gloss)
+        || (single && ADJECTIVE_SUFFIXES.iter().any(|s| gloss.ends_with(s)))
+    {
+        Some(Pos::Adjective)
+    } else if PREPOSITIONS.contains(&gloss) {
+        Some(Pos::Preposition)
+    } else {
+        Some(Pos::Noun)
+    }
+}
+
+/// AA and AABB words like 慢慢 or 高高兴兴.
+fn is_reduplicated(word: &str) -> bool {
+    let chars = word.chars().collect::<Vec<_>>();
+    match chars.as_slice() {
+        [a, b] => a == b,
+        [a, b, c, d] => a == b && c == d,
+        _ => false,
+    }
+}
+
+/// Every part of speech a word might have, most likely first.
+pub fn candidates(defs: &[&cd::WordEntry]) -> Vec<Pos> {
+    let mut result = Vec::new();
+    let mut push = |pos| {
+        if !result.contains(&pos) {
+            result.push(pos)
+        }
+    };
+    let we = match defs.first() {
+        Some(we) => we,
+        None => return vec![Pos::Other],
+    };
+    CLOSED_CLASSES
+        .iter()
+        .filter(|(_, words)| words.contains(&we.simplified.as_str()))
+        .for_each(|(pos, _)| push(*pos));
+    if defs.iter().any(|we| !measure::classifiers(we).is_empty()) {
+        push(Pos::Noun);
+    }
+    defs.iter()
+        .flat_map(|we| we.english.iter())
+        .filter(|sense| !SKIP.is_match(sense))
+        .flat_map(|sense| sense.split(';'))
+        .filter_map(sense_pos)
+        .for_each(&mut push);
+    if result.is_empty() {
+        result.push(Pos::Other);
+    }
+    // Reduplicated descriptive words usually say how something is done.
+    if is_reduplicated(&we.simplified) && matches!(result[0], Pos::Adjective | Pos::Adverb) {
+        result.retain(|pos| *pos != Pos::Adverb);
+        result.insert(0, Pos::Adverb);
+    }
+    result
+}
+
+fn is_demonstrative(seg: &Segment) -> bool {
+    matches!(seg.as_chinese().and_then(|defs| defs.first()),
+        Some(we) if matches!(we.simplified.as_str(), "这" | "那" | "哪" | "几" | "每"))
+}
+
+fn is_word(seg: &Segment, words: &[&str]) -> bool {
+    matches!(seg.as_chinese().and_then(|defs| defs.first()),
+        Some(we) if words.contains(&we.simplified.as_str()))
+}
+
+/// Picks the first of `preferred` that the word can be, if any.
+fn prefer(cands: &[Pos], preferred: &[Pos]) -> Option<Pos> {
+    preferred.iter().copied().find(|pos| cands.contains(pos))
+}
+
+/// Chooses a part of speech for every word, using the words around it to
+/// decide between the ones its definitions allow.
+pub fn tag(segments: &mut [Segment]) {
+    let cands = segments
+        .iter()
+        .map(|seg| seg.as_chinese().map(candidates))
+        .collect::<Vec<_>>();
+
+    for idx in 0..segments.len() {
+        let cands = match &cands[idx] {
+            Some(cands) => cands,
+            None => continue,
+        };
+        let prev = (0..idx)
+            .rev()
+            .find(|idx| !matches!(segments[*idx], Segment::Whitespace(_)))
+            .map(|idx| &segments[idx]);
+        let next = (idx + 1..segments.len())
+            .find(|idx| !matches!(segments[*idx], Segment::Whitespace(_)))
+            .map(|idx| &segments[idx]);
+        let prev_pos = prev.and_then(|seg| seg.pos());
+        let next_ends = matches!(next, None | Some(Segment::Punctuation(_) | Segment::Break));
+        let after_demonstrative = matches!(prev, Some(seg) if is_demonstrative(seg));
+        let after_de = matches!(prev, Some(seg) if is_word(seg, &["的"]));
+        let after_complement = matches!(prev, Some(seg) if is_word(seg, &["得"]));
+        let before_aspect = matches!(next, Some(seg) if is_word(seg, &["了", "着", "过"]));
+
+        let chosen = if cands[0] == Pos::Preposition && next_ends {
+            prefer(cands, &[Pos::Adjective, Pos::Verb])
+        } else if prev_pos == Some(Pos::Numeral) || after_demonstrative {
+            prefer(cands, &[Pos::Measure])
+        } else if after_complement || (prev_pos == Some(Pos::Adverb) && cands[0] != Pos::Adverb) {
+            prefer(cands, &[Pos::Adjective, Pos::Verb])
+        } else if prev_pos == Some(Pos::Measure) || (after_de && cands[0] != Pos::Verb) {
+            prefer(cands, &[Pos::Noun])
+        } else if before_aspect {
+            prefer(cands, &[Pos::Verb])
+        } else if prev_pos == Some(Pos::Pronoun) && cands[0] == Pos::Noun {
+            prefer(cands, &[Pos::Verb, Pos::Adjective])
+        } else {
+            None
+        };
+        if let Segment::Chinese(word) = &mut segments[idx] {
+            word.pos = chosen.unwrap_or(cands[0]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{levels::LevelSystem, words::make_words};
+
+    fn tags(s: &str) -> Vec<Pos> {
+        make_words(s, false, LevelSystem::Hsk2)
+            .0
+            .iter()
+            .filter_map(|seg| seg.pos())
+            .collect()
+    }
+
+    #[test]
+    pub fn test_tagging() {
+        use Pos::*;
+        assert_eq!(
+            tags("我买了三本书。"),
+            vec![Pronoun, Verb, Particle, Numeral, Measure, Noun]
+        );
+        assert_eq!(tags("他花了钱"), vec![Pronoun, Verb, Particle, Noun]);
+        assert_eq!(tags("我的花"), vec![Pronoun, Particle, Noun]);
+        assert_eq!(tags("她慢慢地走"), vec![Pronoun, Adverb, Particle, Verb]);
+        assert_eq!(tags("很漂亮"), vec![Adverb, Adjective]);
+    }
+}
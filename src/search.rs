@@ -0,0 +1,229 @@
+use std::collections::HashSet;
+
+use chinese_dictionary as cd;
+
+use crate::{
+    convert::is_han,
+    dictionary::{dictionary, hsk_level},
+    phonetic as ph,
+    words::{short_gloss, sort_defs},
+};
+
+/// Most results a search returns.
+pub const MAX_RESULTS: usize = 50;
+
+/// Longest pinyin syllable, like zhuang.
+const MAX_SYLLABLE_LEN: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    Auto,
+    Hanzi,
+    Pinyin,
+    English,
+}
+
+impl SearchMode {
+    pub const OPTIONS: &'static [&'static str] = &["auto", "hanzi", "pinyin", "English"];
+}
+
+impl From<usize> for SearchMode {
+    fn from(val: usize) -> Self {
+        match val {
+            1 => Self::Hanzi,
+            2 => Self::Pinyin,
+            3 => Self::English,
+            _ => Self::Auto,
+        }
+    }
+}
+
+/// A pinyin syllable the way the dictionary indexes it, with ü as u:.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchSyllable {
+    pub pinyin: String,
+    pub tone: Option<u8>,
+}
+
+/// Strips a tone mark, returning the plain vowel and the tone.
+fn unmark(c: char) -> (char, Option<u8>) {
+    const MARKED: &[(char, &str)] = &[
+        ('a', "āáǎà"),
+        ('e', "ēéěè"),
+        ('i', "īíǐì"),
+        ('o', "ōóǒò"),
+        ('u', "ūúǔù"),
+        ('ü', "ǖǘǚǜ"),
+    ];
+    MARKED
+        .iter()
+        .find_map(|(base, marks)| {
+            let tone = marks.chars().position(|m| m == c)?;
+            Some((*base, Some(tone as u8 + 1)))
+        })
+        .unwrap_or((c, None))
+}
+
+/// Splits pinyin with or without tones or spaces, like "zhongguo",
+/// "zhong1 guo2" or "zhōngguó", into syllables. Returns `None` if it isn't
+/// pinyin.
+pub fn parse_pinyin(s: &str) -> Option<Vec<SearchSyllable>> {
+    let s = s.to_lowercase().replace("u:", "ü");
+    let mut result = Vec::new();
+    for group in s.split(|c: char| c.is_whitespace() || c == '\'') {
+        parse_pinyin_group(group, &mut result)?;
+    }
+    (!result.is_empty()).then_some(result)
+}
+
+/// Splits a run of pinyin without separators, the longest syllable first.
+fn parse_pinyin_group(s: &str, result: &mut Vec<SearchSyllable>) -> Option<()> {
+    let mut chars = Vec::new();
+    let mut tones = Vec::new();
+    for c in s.chars() {
+        let (c, tone) = unmark(if c == 'v' { 'ü' } else { c });
+        match c {
+            '1'..='5' if !tones.is_empty() => {
+                let last = tones.len() - 1;
+                tones[last] = c.to_digit(10).map(|t| t as u8);
+            }
+            c if c.is_ascii_lowercase() || c == 'ü' => {
+                chars.push(c);
+                tones.push(tone);
+            }
+            _ => return None,
+        }
+    }
+
+    let mut start = 0;
+    while start < chars.len() {
+        // Longest prefix that the phonetic parser reads back unchanged.
+        let end = (start + 1..=(start + MAX_SYLLABLE_LEN).min(chars.len()))
+            .rev()
+            .find(|end| {
+                let candidate = chars[start..*end].iter().collect::<String>();
+                matches!(ph::Syllable::from_pinyin(&candidate), Some(syl)
+                    if syl.pinyin() == candidate)
+            })?;
+        result.push(SearchSyllable {
+            pinyin: chars[start..end]
+                .iter()
+                .collect::<String>()
+                .replace('ü', "u:"),
+            tone: tones[start..end].iter().copied().flatten().last(),
+        });
+        start = end;
+    }
+    Some(())
+}
+
+fn search_pinyin(syllables: &[SearchSyllable]) -> Vec<&'static cd::WordEntry> {
+    if syllables.iter().all(|syl| syl.tone.is_some()) {
+        let key = syllables
+            .iter()
+            .map(|syl| format!("{}{}", syl.pinyin, syl.tone.unwrap_or(5)))
+            .collect::<String>();
+        return dictionary().query_by_pinyin(&key);
+    }
+    let key = syllables
+        .iter()
+        .map(|syl| syl.pinyin.as_str())
+        .collect::<String>();
+    // Only some of the syllables have tones, those still have to match.
+    dictionary()
+        .query_by_pinyin(&key)
+        .into_iter()
+        .filter(|we| {
+            we.tone_marks.len() == syllables.len()
+                && syllables
+                    .iter()
+                    .zip(we.tone_marks.iter())
+                    .all(|(syl, tone)| syl.tone.is_none() || syl.tone == Some(*tone))
+        })
+        .collect()
+}
+
+fn search_hanzi(s: &str) -> Vec<&'static cd::WordEntry> {
+    let mut result = dictionary().query_by_simplified(s);
+    result.extend(dictionary().query_by_traditional(s));
+    sort_defs(&mut result);
+    if result.is_empty() {
+        result = dictionary().query_by_chinese(s);
+    }
+    result
+}
+
+fn search_english(s: &str) -> Vec<&'static cd::WordEntry> {
+    let s = s.trim().to_lowercase();
+    let mut result = dictionary().query_by_english(&s);
+    // Entries that mean exactly the search go first, common and short words
+    // before the rest.
+    result.sort_by_cached_key(|we| {
+        let gloss = short_gloss(we).unwrap_or_default().to_lowercase();
+        let gloss = gloss.strip_prefix("to ").unwrap_or(&gloss).to_owned();
+        let exact = gloss == s || gloss == s.strip_prefix("to ").unwrap_or(&s);
+        (!exact, hsk_level(we) == 0, we.simplified.chars().count())
+    });
+    result
+}
+
+/// Looks up a search in the dictionary. In `Auto` mode Han characters are
+/// looked up as words and anything else as pinyin and then English.
+pub fn search(s: &str, mode: SearchMode) -> Vec<&'static cd::WordEntry> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Vec::new();
+    }
+    let mut result = match mode {
+        SearchMode::Hanzi => search_hanzi(s),
+        SearchMode::Pinyin => parse_pinyin(s)
+            .map(|syls| search_pinyin(&syls))
+            .unwrap_or_default(),
+        SearchMode::English => search_english(s),
+        SearchMode::Auto if s.chars().any(is_han) => search_hanzi(s),
+        SearchMode::Auto => {
+            let mut result = parse_pinyin(s)
+                .map(|syls| search_pinyin(&syls))
+                .unwrap_or_default();
+            result.extend(search_english(s));
+            result
+        }
+    };
+    let mut seen = HashSet::new();
+    result.retain(|we| seen.insert(we.word_id));
+    result.truncate(MAX_RESULTS);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn words(s: &str, mode: SearchMode) -> Vec<&'static str> {
+        search(s, mode)
+            .into_iter()
+            .map(|we| we.simplified.as_str())
+            .collect()
+    }
+
+    #[test]
+    pub fn test_parse_pinyin() {
+        let syls = parse_pinyin("zhōngguó").unwrap();
+        assert_eq!(syls.len(), 2);
+        assert_eq!(syls[0].pinyin, "zhong");
+        assert_eq!(syls[1].tone, Some(2));
+        assert_eq!(parse_pinyin("nv3").unwrap()[0].pinyin, "nu:");
+        assert_eq!(parse_pinyin("xi'an").unwrap().len(), 2);
+        assert_eq!(parse_pinyin("dog"), None);
+    }
+
+    #[test]
+    pub fn test_search() {
+        assert!(words("zhongguo", SearchMode::Pinyin).contains(&"中国"));
+        assert!(words("zhong1 guo2", SearchMode::Auto).contains(&"中国"));
+        assert!(words("Zhōngguó", SearchMode::Pinyin).contains(&"中国"));
+        assert!(words("nü3", SearchMode::Pinyin).contains(&"女"));
+        assert!(words("中國", SearchMode::Auto).contains(&"中国"));
+        assert_eq!(words("cat", SearchMode::English).first(), Some(&"猫"));
+    }
+}
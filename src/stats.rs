@@ -2,13 +2,35 @@
 use std::collections::HashMap;
 use std::iter;
 
-const MAX_HSK_LEVEL: usize = 16;
+use crate::{convert::ScriptCounts, levels::LevelSystem, words::Segment};
+
+/// Number of levels counted, the last one is for words without a level.
+pub const MAX_HSK_LEVEL: usize = 64;
 
 type HskMap = HashMap<&'static str, usize>;
+type CharMap = HashMap<char, usize>;
+
+fn level_index(hsk: u8) -> usize {
+    if hsk >= (MAX_HSK_LEVEL as u8) || hsk == 0 {
+        MAX_HSK_LEVEL - 1
+    } else {
+        (hsk - 1) as usize
+    }
+}
 
 pub struct Stats {
     pub hskwords: Vec<HskMap>,
     pub hskcounts: Vec<usize>,
+    /// Characters of words by the level of the character itself.
+    pub hskchars: Vec<CharMap>,
+    /// Characters that aren't part of any word the dictionary knows.
+    pub uncovered: CharMap,
+    pub unknown: HashMap<String, usize>,
+    pub punctuation: usize,
+    pub numbers: usize,
+    pub latin: usize,
+    pub uncertain: usize,
+    pub script: ScriptCounts,
 }
 
 impl Stats {
@@ -16,24 +38,97 @@ impl Stats {
         Self {
             hskwords: Vec::from_iter(iter::repeat(HskMap::default()).take(MAX_HSK_LEVEL)),
             hskcounts: Vec::from_iter(iter::repeat(0).take(MAX_HSK_LEVEL)),
+            hskchars: Vec::from_iter(iter::repeat(CharMap::default()).take(MAX_HSK_LEVEL)),
+            uncovered: CharMap::default(),
+            unknown: HashMap::default(),
+            punctuation: 0,
+            numbers: 0,
+            latin: 0,
+            uncertain: 0,
+            script: ScriptCounts::default(),
         }
     }
 
     pub fn reset(&mut self) {
         self.hskcounts = Vec::from_iter(iter::repeat(0).take(MAX_HSK_LEVEL));
         self.hskwords.iter_mut().for_each(|hm| hm.clear());
+        self.hskchars.iter_mut().for_each(|cm| cm.clear());
+        self.uncovered.clear();
+        self.unknown.clear();
+        self.punctuation = 0;
+        self.numbers = 0;
+        self.latin = 0;
+        self.uncertain = 0;
+        self.script = ScriptCounts::default();
     }
 
     pub fn update(&mut self, k: &'static str, hsk: u8) {
-        let hsk = if hsk >= (MAX_HSK_LEVEL as u8) || hsk == 0 {
-            MAX_HSK_LEVEL - 1
-        } else {
-            (hsk - 1) as usize
-        };
+        let hsk = level_index(hsk);
         let ent = self.hskwords[hsk].entry(k).or_insert(0);
         *ent += 1;
         self.hskcounts[hsk] += 1;
     }
+
+    pub fn update_char(&mut self, c: char, levels: LevelSystem) {
+        let level = match self.hskchars.iter().position(|cm| cm.contains_key(&c)) {
+            Some(level) => level,
+            None => level_index(levels.char_level(c)),
+        };
+        *self.hskchars[level].entry(c).or_insert(0) += 1;
+    }
+
+    pub fn unique_chars(&self) -> usize {
+        self.hskchars.iter().map(|cm| cm.len()).sum::<usize>() + self.uncovered.len()
+    }
+
+    pub fn total_chars(&self) -> usize {
+        self.hskchars
+            .iter()
+            .chain(iter::once(&self.uncovered))
+            .flat_map(|cm| cm.values())
+            .sum()
+    }
+
+    /// Characters outside HSK or at the highest levels first, the ones seen
+    /// least often first within a level.
+    pub fn rarest_chars(&self, max: usize) -> Vec<(char, usize)> {
+        let mut result = Vec::new();
+        for cm in self.hskchars.iter().rev() {
+            let mut chars = cm.iter().map(|(c, n)| (*c, *n)).collect::<Vec<_>>();
+            chars.sort_by_key(|(c, n)| (*n, *c));
+            result.extend(chars);
+            if result.len() >= max {
+                break;
+            }
+        }
+        result.truncate(max);
+        result
+    }
+
+    pub fn update_segment(&mut self, seg: &Segment, levels: LevelSystem) {
+        match seg {
+            Segment::Chinese(word) => {
+                if let Some(w) = word.defs.first() {
+                    self.update(&w.simplified, levels.level(w));
+                    w.simplified
+                        .chars()
+                        .for_each(|c| self.update_char(c, levels));
+                }
+                if word.uncertain {
+                    self.uncertain += 1;
+                }
+            }
+            Segment::UnknownHan(s) => {
+                *self.unknown.entry(s.to_owned()).or_insert(0) += 1;
+                s.chars()
+                    .for_each(|c| *self.uncovered.entry(c).or_insert(0) += 1);
+            }
+            Segment::Punctuation(_) => self.punctuation += 1,
+            Segment::Number(_) => self.numbers += 1,
+            Segment::Latin(_) => self.latin += 1,
+            Segment::Whitespace(_) | Segment::Plain(_) | Segment::Break => (),
+        }
+    }
 }
 
 impl Default for Stats {
@@ -41,3 +136,18 @@ impl Default for Stats {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{levels::LevelSystem, words::make_words};
+
+    #[test]
+    pub fn test_char_stats() {
+        let (_, stats) = make_words("我和我的麒麟。", false, LevelSystem::Hsk2);
+        assert_eq!(stats.total_chars(), 6);
+        assert_eq!(stats.unique_chars(), 5);
+        assert_eq!(stats.hskchars[0].get(&'我'), Some(&2));
+        assert!(stats.uncovered.is_empty());
+        assert_eq!(stats.rarest_chars(2), vec![('麒', 1), ('麟', 1)]);
+    }
+}
@@ -0,0 +1,387 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use chinese_dictionary as cd;
+use dioxus::prelude::*;
+use futures::prelude::*;
+use once_cell::sync::Lazy;
+
+use crate::{
+    config::Config,
+    convert::is_han,
+    input::{read_file_input, InputAction},
+    levels::LevelSystem,
+    stats::MAX_HSK_LEVEL,
+    words::{is_numeral, numeral_value},
+};
+
+/// Header names of the lesson column of CSV lists.
+const LESSON_HEADERS: &[&str] = &[
+    "lesson", "unit", "chapter", "level", "课", "課", "课文", "課文", "单元", "單元", "章", "级",
+    "級", "等级", "等級",
+];
+/// Header names of the word column of CSV lists.
+const WORD_HEADERS: &[&str] = &[
+    "word",
+    "words",
+    "hanzi",
+    "chinese",
+    "simplified",
+    "traditional",
+    "生词",
+    "生詞",
+    "词语",
+    "詞語",
+    "词",
+    "詞",
+    "单词",
+    "單詞",
+    "汉字",
+    "漢字",
+];
+/// Characters that mark a Chinese lesson tag like 第一课, along with a number.
+const LESSON_MARKERS: &[char] = &['第', '课', '課', '单', '單', '元', '级', '級', '章'];
+
+/// Part of a lesson tag, numbers sort by value and before text.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum TagPart {
+    Number(u64),
+    Text(String),
+}
+
+/// Splits a lesson tag like `Lesson 12` or 第十二课 into text and numbers to
+/// sort by, `None` if it has no number.
+fn tag_key(tag: &str) -> Option<Vec<TagPart>> {
+    let mut parts = Vec::new();
+    let mut rest = tag;
+    while let Some(c) = rest.chars().next() {
+        let numeral = is_numeral(c);
+        let len = rest
+            .find(|c| is_numeral(c) != numeral)
+            .unwrap_or(rest.len());
+        let (run, tail) = rest.split_at(len);
+        parts.push(match numeral_value(run) {
+            Some(n) if numeral => TagPart::Number(n),
+            _ => TagPart::Text(run.to_lowercase()),
+        });
+        rest = tail;
+    }
+    parts
+        .iter()
+        .any(|part| matches!(part, TagPart::Number(_)))
+        .then_some(parts)
+}
+
+/// Whether a field is a lesson tag rather than a word, like `L1`, `3` or 第一课.
+fn is_lesson_tag(field: &str) -> bool {
+    !field.is_empty()
+        && (!field.chars().all(is_han)
+            || (field.contains(LESSON_MARKERS) && tag_key(field).is_some()))
+}
+
+/// Tag of a `# Lesson 1` or `[Lesson 1]` line.
+fn section(line: &str) -> Option<&str> {
+    line.strip_prefix('#')
+        .or_else(|| line.strip_prefix('[').and_then(|l| l.strip_suffix(']')))
+        .map(str::trim)
+}
+
+/// Chinese words of a line of several words.
+fn han_words(line: &str) -> impl Iterator<Item = &str> {
+    line.split([',', '\t', ';', ' '])
+        .filter(|word| word.chars().any(is_han))
+}
+
+fn is_header_name(field: &str, names: &[&str]) -> bool {
+    names.contains(&field.to_lowercase().as_str())
+}
+
+/// A textbook vocabulary, with words tagged by the lesson they're taught in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordList {
+    pub name: String,
+    /// Lesson tags in numeric order if they're all numbered like `Lesson 2`
+    /// or 第二课, otherwise in the order they first appear.
+    pub lessons: Vec<String>,
+    /// Words in either script with their lesson, counting from 1.
+    words: HashMap<String, u8>,
+}
+
+impl WordList {
+    /// Parses a word list, either plain text with `# Lesson 1` or
+    /// `[Lesson 1]` lines starting each lesson, or CSV lines like
+    /// `学习,xuéxí,Lesson 1` with the lesson in the column a header row
+    /// names, or else in the last column if it always holds tags like `L1`
+    /// or 第一课. Other lines are several words. Lists without lessons are
+    /// one lesson.
+    pub fn parse(name: &str, text: &str) -> Result<Self, String> {
+        let lines = text
+            .lines()
+            .map(|line| line.trim_start_matches('\u{feff}').trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let mut words = Vec::new();
+        if lines.iter().any(|line| section(line).is_some()) {
+            let mut current = name;
+            for line in lines {
+                match section(line) {
+                    Some(tag) => current = tag,
+                    None => words.extend(han_words(line).map(|word| (word, current))),
+                }
+            }
+            return Self::from_words(name, words);
+        }
+
+        let rows = lines
+            .iter()
+            .map(|line| {
+                line.split([',', '\t', ';'])
+                    .map(str::trim)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        // A header row like "word,pinyin,lesson" or 生词,课.
+        let header = rows.first().filter(|fields| {
+            fields
+                .iter()
+                .any(|f| is_header_name(f, LESSON_HEADERS) || is_header_name(f, WORD_HEADERS))
+                || (fields.len() > 1 && !fields.iter().any(|f| f.chars().any(is_han)))
+        });
+        let column = |names| header.and_then(|h| h.iter().position(|f| is_header_name(f, names)));
+        let (lesson_column, word_column) = (column(LESSON_HEADERS), column(WORD_HEADERS));
+        let skip = header.map_or(0, |_| 1);
+        // Without a header the last column is the lesson if it always looks
+        // like one, so 学习,第一课 is a tagged word but 你好,谢谢 two words.
+        let tagged_last = header.is_none()
+            && rows.iter().any(|fields| fields.len() > 1)
+            && rows
+                .iter()
+                .all(|fields| fields.len() < 2 || is_lesson_tag(fields[fields.len() - 1]));
+
+        for (line, fields) in lines.iter().zip(&rows).skip(skip) {
+            if fields.len() > 1 && !line.chars().any(is_han) {
+                continue;
+            }
+            let column = match lesson_column {
+                Some(col) => Some(col),
+                None if tagged_last && fields.len() > 1 => Some(fields.len() - 1),
+                None => None,
+            };
+            let tag = column
+                .and_then(|col| Some((col, *fields.get(col)?)))
+                .filter(|(_, tag)| !tag.is_empty());
+            let word = word_column
+                .and_then(|col| fields.get(col))
+                .filter(|word| word.chars().any(is_han));
+            match (tag, word) {
+                (Some((col, tag)), _) => {
+                    let word = word.or_else(|| {
+                        fields
+                            .iter()
+                            .enumerate()
+                            .find(|(idx, f)| *idx != col && f.chars().any(is_han))
+                            .map(|(_, f)| f)
+                    });
+                    words.extend(word.map(|word| (*word, tag)));
+                }
+                (None, Some(word)) => words.push((word, name)),
+                (None, None) => words.extend(han_words(line).map(|word| (word, name))),
+            }
+        }
+        Self::from_words(name, words)
+    }
+
+    /// Builds a list from words and their lesson tags, sorting the lessons
+    /// if they're all numbered. Words keep the earliest lesson they're taught in.
+    fn from_words(name: &str, words: Vec<(&str, &str)>) -> Result<Self, String> {
+        if words.is_empty() {
+            return Err(format!("No words in {name}"));
+        }
+        let mut lessons = Vec::<&str>::new();
+        for (_, tag) in &words {
+            if !lessons.contains(tag) {
+                lessons.push(tag);
+            }
+        }
+        // The last level is for words that aren't in the list.
+        if lessons.len() >= MAX_HSK_LEVEL {
+            return Err(format!("More than {} lessons", MAX_HSK_LEVEL - 1));
+        }
+        let keys = lessons
+            .iter()
+            .map(|tag| tag_key(tag))
+            .collect::<Option<Vec<_>>>();
+        if let Some(keys) = keys {
+            let mut order = keys.into_iter().zip(lessons).collect::<Vec<_>>();
+            order.sort();
+            lessons = order.into_iter().map(|(_, tag)| tag).collect();
+        }
+        let mut list = Self {
+            name: name.to_owned(),
+            lessons: lessons.iter().map(|tag| (*tag).to_owned()).collect(),
+            words: HashMap::new(),
+        };
+        for (word, tag) in words {
+            let lesson = lessons.iter().position(|l| *l == tag).unwrap_or_default() as u8 + 1;
+            let entry = list.words.entry(word.to_owned()).or_insert(lesson);
+            *entry = (*entry).min(lesson);
+        }
+        Ok(list)
+    }
+
+    /// Lesson of a definition, 0 if the list doesn't have it.
+    pub fn level(&self, we: &cd::WordEntry) -> u8 {
+        self.words
+            .get(&we.simplified)
+            .or_else(|| self.words.get(&we.traditional))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn lesson_name(&self, level: u8) -> &str {
+        (level as usize)
+            .checked_sub(1)
+            .and_then(|idx| self.lessons.get(idx))
+            .map_or("", String::as_str)
+    }
+}
+
+static LISTS: Lazy<RwLock<Vec<&'static WordList>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Adds an imported list, replacing one with the same name. Lists are kept
+/// for the rest of the session. Returns the list's index.
+pub fn add_list(list: WordList) -> usize {
+    let mut lists = LISTS
+        .write()
+        .expect("Internal error: Word list lock poisoned");
+    let list = Box::leak(Box::new(list));
+    match lists.iter().position(|other| other.name == list.name) {
+        Some(idx) => {
+            lists[idx] = list;
+            idx
+        }
+        None => {
+            lists.push(list);
+            lists.len() - 1
+        }
+    }
+}
+
+pub fn list(idx: usize) -> Option<&'static WordList> {
+    LISTS
+        .read()
+        .expect("Internal error: Word list lock poisoned")
+        .get(idx)
+        .copied()
+}
+
+pub fn names() -> Vec<String> {
+    LISTS
+        .read()
+        .expect("Internal error: Word list lock poisoned")
+        .iter()
+        .map(|list| list.name.clone())
+        .collect()
+}
+
+pub enum WordListAction {
+    /// Imports the file chosen in the word list file input.
+    Upload,
+    /// Imports pasted text.
+    Paste { name: String, text: String },
+}
+
+/// Imports word lists and switches to the imported list.
+pub async fn wordlist_service(
+    mut rx: UnboundedReceiver<WordListAction>,
+    cfg: UseRef<Config>,
+    status: UseRef<String>,
+    input_task: CoroutineHandle<InputAction>,
+) {
+    while let Some(msg) = rx.next().await {
+        let file = match msg {
+            WordListAction::Upload => read_file_input("wordlistfile").await.map(|(name, text)| {
+                // The file name without its extension.
+                let name = match name.rsplit_once('.') {
+                    Some((stem, _)) if !stem.is_empty() => stem.to_owned(),
+                    _ => name,
+                };
+                (name, text)
+            }),
+            WordListAction::Paste { name, text } if name.trim().is_empty() => {
+                Ok(("Word list".to_owned(), text))
+            }
+            WordListAction::Paste { name, text } => Ok((name.trim().to_owned(), text)),
+        };
+        match file.and_then(|(name, text)| WordList::parse(&name, &text)) {
+            Ok(list) => {
+                status.set(format!(
+                    "(imported {}: {} words, {} lessons)",
+                    list.name,
+                    list.words.len(),
+                    list.lessons.len()
+                ));
+                cfg.write().levels = LevelSystem::Custom(add_list(list));
+                input_task.send(InputAction::Refresh);
+            }
+            Err(err) => status.set(format!("({err})")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_word_list() {
+        let list = WordList::parse(
+            "Book 1",
+            "# Lesson 1\n你好 谢谢\n我\n\n[Lesson 2]\n学习\n你好\n",
+        )
+        .unwrap();
+        assert_eq!(list.lessons, vec!["Lesson 1", "Lesson 2"]);
+        assert_eq!(list.level(cd::query_by_simplified("谢谢")[0]), 1);
+        assert_eq!(list.level(cd::query_by_simplified("学习")[0]), 2);
+        assert_eq!(list.level(cd::query_by_simplified("你好")[0]), 1);
+        assert_eq!(list.level(cd::query_by_simplified("猫")[0]), 0);
+        assert_eq!(list.lesson_name(2), "Lesson 2");
+
+        // Lessons are sorted by number rather than by first appearance.
+        let list = WordList::parse("csv", "word,lesson\n學習,L3\n猫,L1\n").unwrap();
+        assert_eq!(list.lessons, vec!["L1", "L3"]);
+        assert_eq!(list.level(cd::query_by_simplified("学习")[0]), 2);
+
+        let list = WordList::parse("cols", "学习,xuéxí,L2\n猫\tmāo\tL1\n").unwrap();
+        assert_eq!(list.lessons, vec!["L1", "L2"]);
+        assert_eq!(list.level(cd::query_by_simplified("猫")[0]), 1);
+        let text = "Lesson,Word,Pinyin\nL1,你好,nǐhǎo\nL2,学习,xuéxí\n";
+        let list = WordList::parse("header", text).unwrap();
+        assert_eq!(list.lessons, vec!["L1", "L2"]);
+        assert_eq!(list.level(cd::query_by_simplified("学习")[0]), 2);
+
+        let list = WordList::parse("words", "# L1\n你好,谢谢\n我\t你\n").unwrap();
+        assert_eq!(list.lessons, vec!["L1"]);
+        assert_eq!(list.level(cd::query_by_simplified("谢谢")[0]), 1);
+        assert_eq!(list.level(cd::query_by_simplified("你")[0]), 1);
+
+        let text = "生词,课\n学习,第十课\n猫,第二课\n你好,第一课\n";
+        let list = WordList::parse("chinese", text).unwrap();
+        assert_eq!(list.lessons, vec!["第一课", "第二课", "第十课"]);
+        assert_eq!(list.level(cd::query_by_simplified("学习")[0]), 3);
+        assert_eq!(list.level(cd::query_by_simplified("生词")[0]), 0);
+        let list = WordList::parse("untitled", "学习,第一课\n猫,第二课\n").unwrap();
+        assert_eq!(list.lessons, vec!["第一课", "第二课"]);
+        assert_eq!(list.level(cd::query_by_simplified("学习")[0]), 1);
+        let list = WordList::parse("pairs", "你好,谢谢\n学习,上课\n").unwrap();
+        assert_eq!(list.lessons, vec!["pairs"]);
+        assert_eq!(list.level(cd::query_by_simplified("上课")[0]), 1);
+
+        // Lessons without numbers keep their order.
+        let list = WordList::parse("topics", "# Greetings\n你好\n# Food\n米饭\n").unwrap();
+        assert_eq!(list.lessons, vec!["Greetings", "Food"]);
+
+        let list = WordList::parse("plain", "我\n你").unwrap();
+        assert_eq!(list.lessons, vec!["plain"]);
+        assert!(WordList::parse("empty", "word,lesson\n").is_err());
+    }
+}
@@ -1,22 +1,44 @@
 #![allow(non_snake_case)]
-use std::{borrow::Cow, cmp::Ordering};
+use std::{borrow::Cow, cmp::Ordering, collections::HashSet};
 
 use crate::phonetic::Initial;
 use chinese_dictionary as cd;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
-use crate::{config::Hint, phonetic as ph, stats::Stats};
+use crate::{
+    characters,
+    config::Hint,
+    convert::{Script, ScriptCounts},
+    dictionary::dictionary,
+    frequency,
+    levels::LevelSystem,
+    phonetic as ph,
+    pos::{self, Pos},
+    stats::Stats,
+};
 
+/// Longest sub-word tried when splitting up unrecognized tokens.
+const MAX_SUBWORD_LEN: usize = 4;
+
+/// Shortest and longest chengyu in characters, like 一心一意 and
+/// 醉翁之意不在酒.
+const MIN_IDIOM_LEN: usize = 4;
+const MAX_IDIOM_LEN: usize = 8;
+
+/// Hint for a syllable. Hints about the whole word are `None` here, see
+/// `word_hint`.
 pub fn generate_hint(
     hint: Hint,
+    hanzi: char,
     phon: &ph::Syllable,
     tone: u8,
-    hsk: u8,
     pin: &'static str,
 ) -> Option<Cow<'static, str>> {
     match hint {
-        Hint::Off => None,
+        Hint::Off | Hint::Hsk | Hint::Pos | Hint::Gloss | Hint::OtherScript | Hint::Frequency => {
+            None
+        }
         Hint::Pinyin => Some(Cow::from(phon.pinyin())),
         Hint::PinyinInit => Some(if phon.init != Initial::Hh {
             Cow::from(phon.init.pinyin())
@@ -46,14 +68,135 @@ pub fn generate_hint(
             Cow::from(result)
         }),
         Hint::ToneMark => Some(Cow::from(tone.to_string())),
-        Hint::Hsk => Some(Cow::from(hsk.to_string())),
         Hint::PinyinTM => Some(Cow::from(pin)),
+        Hint::Radical => characters::info(hanzi).map(|info| Cow::from(info.radical.to_string())),
+        Hint::Strokes => characters::info(hanzi).map(|info| Cow::from(info.strokes.to_string())),
+    }
+}
+
+/// Hint for a whole word, `None` for hints about syllables. `level` is the
+/// word's level as the level system writes it.
+pub fn word_hint(
+    hint: Hint,
+    word: &Word,
+    level: &str,
+    simplified: bool,
+    glosslen: usize,
+) -> Option<String> {
+    let we = word.defs.first()?;
+    let hint = match hint {
+        Hint::Hsk => level.to_owned(),
+        Hint::Pos => word.pos.abbr().to_owned(),
+        Hint::Gloss => interlinear_gloss(&word.defs, glosslen)?,
+        Hint::OtherScript if we.simplified == we.traditional => return None,
+        Hint::OtherScript if simplified => we.traditional.clone(),
+        Hint::OtherScript => we.simplified.clone(),
+        Hint::Frequency => format!("#{}", frequency::rank(&we.simplified)?),
+        _ => return None,
+    };
+    (!hint.is_empty()).then_some(hint)
+}
+
+#[derive(Debug, Clone)]
+pub struct Word {
+    pub defs: Vec<&'static cd::WordEntry>,
+    /// Set when the word came from splitting up a token the dictionary
+    /// didn't recognize, so the segmentation is only a guess.
+    pub uncertain: bool,
+    /// Chengyu or other set phrase.
+    pub idiom: bool,
+    /// Guessed part of speech, see `pos::tag`.
+    pub pos: Pos,
+}
+
+impl Word {
+    pub fn new(defs: Vec<&'static cd::WordEntry>) -> Self {
+        Self {
+            idiom: is_idiom(&defs),
+            pos: pos::candidates(&defs)[0],
+            defs,
+            uncertain: false,
+        }
+    }
+}
+
+/// Whether the definitions are for a chengyu, either marked as an idiom by
+/// the dictionary or in the bundled list of ones that aren't.
+pub fn is_idiom(defs: &[&'static cd::WordEntry]) -> bool {
+    static IDIOMS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+        include_str!("../assets/chengyu.txt")
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .collect()
+    });
+    let we = match defs.first() {
+        Some(we) => we,
+        None => return false,
+    };
+    (MIN_IDIOM_LEN..=MAX_IDIOM_LEN).contains(&we.simplified.chars().count())
+        && (IDIOMS.contains(we.simplified.as_str())
+            || defs
+                .iter()
+                .any(|we| we.english.iter().any(|e| e.contains("(idiom)"))))
+}
+
+/// A short English gloss from the first real sense of a definition, with
+/// parentheticals removed.
+pub fn short_gloss(we: &cd::WordEntry) -> Option<String> {
+    static PARENS: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"\([^)]*\)|\[[^]]*\]").expect("Internal error: Could not compile regex")
+    });
+    static SKIP: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?i)^(?:surname|variant of|old variant|used in|see |abbr\. for|CL:)")
+            .expect("Internal error: Could not compile regex")
+    });
+    we.english.iter().find_map(|sense| {
+        let sense = PARENS.replace_all(sense, "");
+        let sense = sense.split(';').next().unwrap_or_default().trim();
+        (!sense.is_empty() && !SKIP.is_match(sense)).then(|| sense.to_owned())
+    })
+}
+
+/// A gloss to show under a word, from the first definition that has one,
+/// shortened to at most `max_len` characters unless that's 0.
+pub fn interlinear_gloss(defs: &[&cd::WordEntry], max_len: usize) -> Option<String> {
+    let gloss = defs.iter().find_map(|we| short_gloss(we))?;
+    if max_len == 0 || gloss.chars().count() <= max_len {
+        return Some(gloss);
     }
+    // One character is left for the ellipsis.
+    let cut = gloss.chars().take(max_len - 1).collect::<String>();
+    // Cut at the end of a word where there's one.
+    let cut = match cut.rfind(' ') {
+        Some(idx) if idx > 0 => &cut[..idx],
+        _ => &cut,
+    };
+    Some(format!("{}…", cut.trim_end_matches([',', ' '])))
+}
+
+/// Character by character gloss of a word, like 画 draw · 蛇 snake.
+pub fn literal_gloss(word: &str) -> String {
+    split_chars(word)
+        .map(|c| {
+            let gloss = lookup(c, false)
+                .iter()
+                .find_map(|we| short_gloss(we))
+                .unwrap_or_else(|| "?".to_owned());
+            format!("{c} {gloss}")
+        })
+        .collect::<Vec<_>>()
+        .join(" · ")
 }
 
 #[derive(Debug, Clone)]
 pub enum Segment {
-    Chinese(Vec<&'static cd::WordEntry>),
+    Chinese(Word),
+    Punctuation(String),
+    Number(Number),
+    Latin(String),
+    Whitespace(String),
+    UnknownHan(String),
     Plain(String),
     Break,
 }
@@ -61,11 +204,22 @@ pub enum Segment {
 impl PartialEq for Segment {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (Self::Chinese(l0), Self::Chinese(r0)) => l0
-                .iter()
-                .zip(r0.iter())
-                .all(|(wel, wer)| wel.word_id == wer.word_id),
-            (Self::Plain(l0), Self::Plain(r0)) => l0 == r0,
+            (Self::Chinese(l0), Self::Chinese(r0)) => {
+                l0.uncertain == r0.uncertain
+                    && l0.idiom == r0.idiom
+                    && l0.pos == r0.pos
+                    && l0
+                        .defs
+                        .iter()
+                        .zip(r0.defs.iter())
+                        .all(|(wel, wer)| wel.word_id == wer.word_id)
+            }
+            (Self::Number(l0), Self::Number(r0)) => l0 == r0,
+            (Self::Punctuation(l0), Self::Punctuation(r0))
+            | (Self::Latin(l0), Self::Latin(r0))
+            | (Self::Whitespace(l0), Self::Whitespace(r0))
+            | (Self::UnknownHan(l0), Self::UnknownHan(r0))
+            | (Self::Plain(l0), Self::Plain(r0)) => l0 == r0,
             (Self::Break, Self::Break) => true,
             _ => false,
         }
@@ -76,7 +230,7 @@ impl PartialEq for Segment {
 impl Segment {
     pub fn as_chinese(&self) -> Option<&[&'static cd::WordEntry]> {
         match self {
-            Segment::Chinese(ref v) => Some(v),
+            Segment::Chinese(ref w) => Some(&w.defs),
             _ => None,
         }
     }
@@ -87,9 +241,415 @@ impl Segment {
             _ => None,
         }
     }
+
+    /// The guessed part of speech of words and numbers.
+    pub fn pos(&self) -> Option<Pos> {
+        match self {
+            Segment::Chinese(w) => Some(w.pos),
+            Segment::Number(_) => Some(Pos::Numeral),
+            _ => None,
+        }
+    }
+
+    /// The literal text of segments that aren't dictionary words.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Segment::Number(n) => Some(n.text.as_str()),
+            Segment::Punctuation(s)
+            | Segment::Latin(s)
+            | Segment::Whitespace(s)
+            | Segment::UnknownHan(s)
+            | Segment::Plain(s) => Some(s.as_str()),
+            Segment::Chinese(_) | Segment::Break => None,
+        }
+    }
+}
+
+/// A syllable of a reading computed rather than taken from the dictionary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reading {
+    pub pinyin: &'static str,
+    pub marks: &'static str,
+    pub tone: u8,
+    /// The numeral read, filled in by `read_hanzi`.
+    pub hanzi: char,
+}
+
+impl Reading {
+    const fn new(pinyin: &'static str, marks: &'static str, tone: u8) -> Self {
+        Self {
+            pinyin,
+            marks,
+            tone,
+            hanzi: '〇',
+        }
+    }
 }
 
-fn sort_defs(defs: &mut [&cd::WordEntry]) {
+/// A number, date or time along with how it's read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Number {
+    pub text: String,
+    /// The number as it would be read, written with Chinese numerals.
+    pub hanzi: String,
+    pub readings: Vec<Reading>,
+    /// Numeric value of numbers written with Chinese numerals.
+    pub value: Option<String>,
+    /// Each character of the text is read as one syllable, like in years
+    /// or numbers written with Chinese numerals.
+    pub aligned: bool,
+}
+
+const NUMBER_SUFFIXES: &str = "%％年月日号號点點岁歲";
+
+/// Numeral runs that are (usually) words rather than numbers.
+const NUMBER_EXCLUSIONS: &[&str] = &["万一", "萬一", "千万", "千萬", "万万", "萬萬", "一一"];
+
+fn numeral_reading(c: char) -> Option<Reading> {
+    Some(match c {
+        '〇' | '零' => Reading::new("ling2", "líng", 2),
+        '一' => Reading::new("yi1", "yī", 1),
+        '二' => Reading::new("er4", "èr", 4),
+        '两' | '兩' => Reading::new("liang3", "liǎng", 3),
+        '三' => Reading::new("san1", "sān", 1),
+        '四' => Reading::new("si4", "sì", 4),
+        '五' => Reading::new("wu3", "wǔ", 3),
+        '六' => Reading::new("liu4", "liù", 4),
+        '七' => Reading::new("qi1", "qī", 1),
+        '八' => Reading::new("ba1", "bā", 1),
+        '九' => Reading::new("jiu3", "jiǔ", 3),
+        '十' => Reading::new("shi2", "shí", 2),
+        '百' => Reading::new("bai3", "bǎi", 3),
+        '千' => Reading::new("qian1", "qiān", 1),
+        '万' | '萬' => Reading::new("wan4", "wàn", 4),
+        '亿' | '億' => Reading::new("yi4", "yì", 4),
+        '点' | '點' => Reading::new("dian3", "diǎn", 3),
+        '第' => Reading::new("di4", "dì", 4),
+        '年' => Reading::new("nian2", "nián", 2),
+        '月' => Reading::new("yue4", "yuè", 4),
+        '日' => Reading::new("ri4", "rì", 4),
+        '号' | '號' => Reading::new("hao4", "hào", 4),
+        '岁' | '歲' => Reading::new("sui4", "suì", 4),
+        '分' => Reading::new("fen1", "fēn", 1),
+        '之' => Reading::new("zhi1", "zhī", 1),
+        _ => return None,
+    })
+}
+
+fn numeral_digit(c: char) -> Option<u64> {
+    Some(match c {
+        '〇' | '零' => 0,
+        '一' => 1,
+        '二' | '两' | '兩' => 2,
+        '三' => 3,
+        '四' => 4,
+        '五' => 5,
+        '六' => 6,
+        '七' => 7,
+        '八' => 8,
+        '九' => 9,
+        _ => return None,
+    })
+}
+
+fn arabic_digit(c: char) -> Option<u32> {
+    c.to_digit(10)
+        .or_else(|| ('０'..='９').contains(&c).then(|| c as u32 - '０' as u32))
+}
+
+fn digit_hanzi(d: u32, zero: char) -> char {
+    ['〇', '一', '二', '三', '四', '五', '六', '七', '八', '九']
+        .get(d as usize)
+        .map(|c| if d == 0 { zero } else { *c })
+        .unwrap_or(zero)
+}
+
+/// Reads a string of Chinese numerals, applying 一 tone sandhi before units.
+fn read_hanzi(s: &str) -> Vec<Reading> {
+    let chars = s.chars().collect::<Vec<_>>();
+    chars
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, c)| {
+            let reading = match (c, chars.get(idx + 1)) {
+                ('一', Some('百' | '千')) => Reading::new("yi4", "yì", 4),
+                ('一', Some('万' | '萬' | '亿' | '億')) => Reading::new("yi2", "yí", 2),
+                _ => numeral_reading(*c)?,
+            };
+            Some(Reading {
+                hanzi: *c,
+                ..reading
+            })
+        })
+        .collect()
+}
+
+/// Writes an integer the way it's read, for example 一百零五.
+fn int_to_hanzi(mut n: u64) -> String {
+    const GROUP_UNITS: &[&str] = &["", "万", "亿", "万亿"];
+    if n == 0 {
+        return "零".to_owned();
+    }
+    let mut groups = Vec::with_capacity(4);
+    while n > 0 {
+        groups.push(n % 10_000);
+        n /= 10_000;
+    }
+    let mut result = String::new();
+    let mut need_zero = false;
+    for (idx, group) in groups.into_iter().enumerate().rev() {
+        if group == 0 {
+            need_zero = !result.is_empty();
+            continue;
+        }
+        if !result.is_empty() && (need_zero || group < 1000) {
+            result.push('零');
+        }
+        let mut zero_pending = false;
+        let mut started = false;
+        for (digit, unit) in [
+            (group / 1000, "千"),
+            (group / 100 % 10, "百"),
+            (group / 10 % 10, "十"),
+            (group % 10, ""),
+        ] {
+            if digit == 0 {
+                zero_pending = started;
+                continue;
+            }
+            if zero_pending {
+                result.push('零');
+                zero_pending = false;
+            }
+            // 十五 rather than 一十五 at the start of a number.
+            if !(digit == 1 && unit == "十" && !started && result.is_empty()) {
+                result.push(digit_hanzi(digit as u32, '零'));
+            }
+            result.push_str(unit);
+            started = true;
+        }
+        result.push_str(GROUP_UNITS.get(idx).copied().unwrap_or_default());
+        need_zero = false;
+    }
+    let mut chars = result.chars();
+    if let (Some('二'), Some('百' | '千' | '万' | '亿')) = (chars.next(), chars.next()) {
+        result.replace_range(0..'二'.len_utf8(), "两");
+    }
+    result
+}
+
+/// Parses a number written with Chinese numerals.
+fn parse_hanzi(s: &str) -> Option<u64> {
+    let has_units = s
+        .chars()
+        .any(|c| matches!(c, '十' | '百' | '千' | '万' | '萬' | '亿' | '億'));
+    if !has_units {
+        // Digit by digit like 二〇二二, too short and it's probably a
+        // word or an approximation like 三四.
+        if s.chars().count() < 3 || s.contains(['两', '兩']) {
+            return None;
+        }
+        return s.chars().try_fold(0u64, |acc, c| {
+            acc.checked_mul(10)?.checked_add(numeral_digit(c)?)
+        });
+    }
+    let (mut total, mut section, mut digit) = (0u64, 0u64, None);
+    for c in s.chars() {
+        if let Some(d) = numeral_digit(c) {
+            match digit {
+                Some(prev) if prev != 0 => return None,
+                _ => digit = Some(d),
+            }
+            continue;
+        }
+        let d = digit.take();
+        match c {
+            '十' => section += d.unwrap_or(1) * 10,
+            '百' => section += d? * 100,
+            '千' => section += d? * 1000,
+            '万' | '萬' => {
+                total = total.saturating_add((section + d.unwrap_or(0)).saturating_mul(10_000));
+                section = 0;
+            }
+            '亿' | '億' => {
+                total = total
+                    .saturating_add(section + d.unwrap_or(0))
+                    .saturating_mul(100_000_000);
+                section = 0;
+            }
+            _ => return None,
+        }
+    }
+    Some(total.saturating_add(section + digit.unwrap_or(0)))
+}
+
+/// Whether a character can be part of a number, in Chinese numerals or
+/// Arabic digits.
+pub fn is_numeral(c: char) -> bool {
+    numeral_digit(c).is_some()
+        || arabic_digit(c).is_some()
+        || matches!(c, '十' | '百' | '千' | '万' | '萬' | '亿' | '億')
+}
+
+/// Value of a run of numerals like 三, 十二 or 12.
+pub fn numeral_value(s: &str) -> Option<u64> {
+    let mut chars = s.chars();
+    match (chars.next()?, chars.next()) {
+        (c, None) => numeral_digit(c)
+            .or_else(|| arabic_digit(c).map(u64::from))
+            .or_else(|| (c == '十').then_some(10)),
+        _ if s.chars().all(|c| arabic_digit(c).is_some()) => s.chars().try_fold(0u64, |acc, c| {
+            acc.checked_mul(10)?
+                .checked_add(u64::from(arabic_digit(c)?))
+        }),
+        _ => parse_hanzi(s),
+    }
+}
+
+fn ordinal(n: u64) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+impl Number {
+    fn new(text: &str, hanzi: String, value: Option<String>, aligned: bool) -> Self {
+        Self {
+            text: text.to_owned(),
+            readings: read_hanzi(&hanzi),
+            hanzi,
+            value,
+            aligned,
+        }
+    }
+
+    /// Recognizes a number written with Chinese numerals like 一百二十三,
+    /// 第十 or 三点五.
+    pub fn from_hanzi(text: &str) -> Option<Self> {
+        if NUMBER_EXCLUSIONS.contains(&text) {
+            return None;
+        }
+        let (is_ordinal, body) = match text.strip_prefix('第') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        if body.is_empty() || (!is_ordinal && body.chars().nth(1).is_none()) {
+            return None;
+        }
+        let (int, frac) = match body.split_once(['点', '點']) {
+            Some((int, frac)) => (int, Some(frac)),
+            None => (body, None),
+        };
+        let n = if int.chars().nth(1).is_none() {
+            numeral_digit(int.chars().next()?).or_else(|| (int == "十").then_some(10))?
+        } else {
+            parse_hanzi(int)?
+        };
+        let value = match frac {
+            _ if is_ordinal => ordinal(n),
+            Some(frac) => format!(
+                "{n}.{}",
+                frac.chars()
+                    .map(|c| numeral_digit(c).map(|d| d.to_string()))
+                    .collect::<Option<String>>()?
+            ),
+            None => n.to_string(),
+        };
+        Some(Self::new(text, text.to_owned(), Some(value), true))
+    }
+
+    /// Builds a number written with Arabic numerals, optionally with a
+    /// 第 prefix or a unit suffix like 年 or %. `classifier` is set when a
+    /// measure word follows, so 2 is read as 两.
+    pub fn from_arabic(text: &str, classifier: bool) -> Self {
+        let (is_ordinal, body) = match text.strip_prefix('第') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (body, suffix) = match body.chars().last() {
+            Some(c) if NUMBER_SUFFIXES.contains(c) => (&body[..body.len() - c.len_utf8()], Some(c)),
+            _ => (body, None),
+        };
+        let digits = |s: &str| s.chars().filter_map(arabic_digit).collect::<Vec<_>>();
+        let to_int = |ds: &[u32]| {
+            (ds.len() <= 16).then(|| ds.iter().fold(0u64, |acc, d| acc * 10 + *d as u64))
+        };
+        let read_digits = |ds: &[u32]| ds.iter().map(|d| digit_hanzi(*d, '〇')).collect::<String>();
+        let liang = |hanzi: String| {
+            if hanzi == "二" {
+                "两".to_owned()
+            } else {
+                hanzi
+            }
+        };
+
+        let mut hanzi = String::new();
+        if is_ordinal {
+            hanzi.push('第');
+        }
+        if let Some((hours, minutes)) = body.split_once([':', '：']) {
+            let (hours, minutes) = (to_int(&digits(hours)), to_int(&digits(minutes)));
+            hanzi.push_str(&liang(int_to_hanzi(hours.unwrap_or_default())));
+            hanzi.push('点');
+            match minutes.unwrap_or_default() {
+                0 => (),
+                m @ 1..=9 => {
+                    hanzi.push('零');
+                    hanzi.push_str(&int_to_hanzi(m));
+                }
+                m => hanzi.push_str(&int_to_hanzi(m)),
+            }
+            return Self::new(text, hanzi, None, false);
+        }
+        let (int, frac) = match body.split_once(['.', '．']) {
+            Some((int, frac)) => (digits(int), Some(digits(frac))),
+            None => (digits(body), None),
+        };
+        let (mut numhanzi, by_digit) = match (suffix, to_int(&int)) {
+            (Some('年'), _) if frac.is_none() => (read_digits(&int), true),
+            (_, Some(n)) => (int_to_hanzi(n), false),
+            (_, None) => (read_digits(&int), true),
+        };
+        // Only numbers read digit by digit line up with their syllables.
+        let aligned = by_digit && frac.is_none() && !matches!(suffix, Some('%' | '％'));
+        if frac.is_none()
+            && !is_ordinal
+            && (classifier || matches!(suffix, Some('点' | '點' | '岁' | '歲')))
+        {
+            numhanzi = liang(numhanzi);
+        }
+        if let Some(frac) = frac {
+            numhanzi.push('点');
+            frac.iter()
+                .for_each(|d| numhanzi.push(digit_hanzi(*d, '零')));
+        }
+        match suffix {
+            Some('%' | '％') => {
+                hanzi.push_str("百分之");
+                hanzi.push_str(&numhanzi);
+            }
+            Some(c) => {
+                hanzi.push_str(&numhanzi);
+                hanzi.push(c);
+            }
+            None => hanzi.push_str(&numhanzi),
+        }
+        Self::new(text, hanzi, None, aligned)
+    }
+}
+
+/// Whether a word is a measure word, so a preceding 2 should be read as 两.
+fn is_classifier(defs: &[&'static cd::WordEntry]) -> bool {
+    defs.iter()
+        .any(|we| we.english.iter().any(|e| e.contains("classifier")))
+}
+
+pub fn sort_defs(defs: &mut [&cd::WordEntry]) {
     static SUX: Lazy<Regex> = Lazy::new(|| {
         Regex::new(r"(?i)^\s*(:?(?:archaic|old)\s+variant\s+)|(?:\(archaic\))\s*$")
             .expect("Internal error: Could not compile regex")
@@ -115,54 +675,433 @@ fn sort_defs(defs: &mut [&cd::WordEntry]) {
     });
 }
 
-pub fn make_words(s: &str) -> (Vec<Segment>, Stats) {
+/// Tokenizes a run of Han characters. Unlike `cd::tokenize` this doesn't
+/// drop characters the tokenizer doesn't know, they're returned as single
+/// character tokens.
+pub fn tokenize(s: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut offset = 0;
+    for tok in dictionary().tokenize(s) {
+        let pos = match s[offset..].find(tok) {
+            Some(pos) => offset + pos,
+            None => continue,
+        };
+        result.extend(split_chars(&s[offset..pos]));
+        result.push(&s[pos..pos + tok.len()]);
+        offset = pos + tok.len();
+    }
+    result.extend(split_chars(&s[offset..]));
+    result
+}
+
+fn split_chars(s: &str) -> impl Iterator<Item = &str> {
+    s.char_indices()
+        .map(move |(idx, c)| &s[idx..idx + c.len_utf8()])
+}
+
+/// Looks up a word, trying Traditional first when `traditional` is set and
+/// falling back to the other script.
+pub fn lookup(chword: &str, traditional: bool) -> Vec<&'static cd::WordEntry> {
+    let mut qr = if traditional {
+        dictionary().query_by_traditional(chword)
+    } else {
+        dictionary().query_by_simplified(chword)
+    };
+    if qr.is_empty() {
+        if traditional {
+            qr = dictionary().query_by_simplified(chword);
+        } else if cd::is_traditional(chword) {
+            qr = dictionary().query_by_traditional(chword);
+        }
+    }
+    sort_defs(&mut qr);
+    qr
+}
+
+fn make_unknown_segment(chword: &str) -> Segment {
+    Number::from_hanzi(chword)
+        .map(Segment::Number)
+        .unwrap_or_else(|| Segment::UnknownHan(chword.to_owned()))
+}
+
+/// Splits a token the dictionary doesn't know into the longest known
+/// sub-words, falling back to individual characters.
+fn split_unknown(chword: &str, traditional: bool) -> Vec<Segment> {
+    let chars = chword
+        .char_indices()
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+    let mut result = Vec::with_capacity(chars.len());
+    let mut start = 0;
+
+    while start < chars.len() {
+        let found = (start + 1..chars.len().min(start + MAX_SUBWORD_LEN) + 1)
+            .rev()
+            .filter(|end| *end - start < chars.len())
+            .find_map(|end| {
+                let byteend = chars.get(end).copied().unwrap_or(chword.len());
+                let defs = lookup(&chword[chars[start]..byteend], traditional);
+                (!defs.is_empty()).then_some((end, defs))
+            });
+        match found {
+            Some((end, defs)) => {
+                result.push(Segment::Chinese(Word {
+                    uncertain: true,
+                    ..Word::new(defs)
+                }));
+                start = end;
+            }
+            None => {
+                let byteend = chars.get(start + 1).copied().unwrap_or(chword.len());
+                result.push(make_unknown_segment(&chword[chars[start]..byteend]));
+                start += 1;
+            }
+        }
+    }
+    result
+}
+
+fn make_han_segments(chword: &str, traditional: bool) -> Vec<Segment> {
+    let qr = lookup(chword, traditional);
+    if !qr.is_empty() {
+        vec![Segment::Chinese(Word::new(qr))]
+    } else if chword.chars().nth(1).is_some() {
+        split_unknown(chword, traditional)
+    } else {
+        vec![make_unknown_segment(chword)]
+    }
+}
+
+/// Finds the first chengyu in a run of Han characters, so it can be kept
+/// as one word even if the tokenizer would split it up.
+fn find_idiom(s: &str, traditional: bool) -> Option<(usize, usize, Vec<&'static cd::WordEntry>)> {
+    let starts = s.char_indices().map(|(idx, _)| idx).collect::<Vec<_>>();
+    (0..starts.len()).find_map(|idx| {
+        (MIN_IDIOM_LEN..=MAX_IDIOM_LEN.min(starts.len() - idx))
+            .rev()
+            .find_map(|len| {
+                let (start, end) = (
+                    starts[idx],
+                    starts.get(idx + len).copied().unwrap_or(s.len()),
+                );
+                let defs = lookup(&s[start..end], traditional);
+                is_idiom(&defs).then_some((start, end, defs))
+            })
+    })
+}
+
+/// Segments a run of Han characters, picking out chengyu and numbers
+/// written with Chinese numerals before tokenizing the rest.
+fn make_han_chunk(s: &str, traditional: bool) -> Vec<Segment> {
+    let mut result = Vec::new();
+    let mut rest = s;
+    while let Some((start, end, defs)) = find_idiom(rest, traditional) {
+        result.extend(make_numbers_and_words(&rest[..start], traditional));
+        result.push(Segment::Chinese(Word::new(defs)));
+        rest = &rest[end..];
+    }
+    result.extend(make_numbers_and_words(rest, traditional));
+    result
+}
+
+fn make_numbers_and_words(s: &str, traditional: bool) -> Vec<Segment> {
+    static NUMERALS: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"第?[〇零一二三四五六七八九十百千万萬亿億两兩]+(?:[点點][〇零一二三四五六七八九]+)?",
+        )
+        .expect("Internal error: Could not compile regex")
+    });
+    let mut result = Vec::new();
+    let mut offset = 0;
+    let push_words = |result: &mut Vec<Segment>, txt: &str| {
+        tokenize(txt)
+            .into_iter()
+            .for_each(|tok| result.extend(make_han_segments(tok, traditional)))
+    };
+
+    for m in NUMERALS.find_iter(s) {
+        // Decimals that run on into a word, like 一点一点 "little by little"
+        // or 一点一滴, are that word rather than 1.1.
+        let next = s[m.end()..].chars().next().map_or(0, char::len_utf8);
+        if m.as_str().contains(['点', '點'])
+            && next > 0
+            && !lookup(&s[m.start()..m.end() + next], traditional).is_empty()
+        {
+            continue;
+        }
+        if let Some(num) = Number::from_hanzi(m.as_str()) {
+            push_words(&mut result, &s[offset..m.start()]);
+            result.push(Segment::Number(num));
+            offset = m.end();
+        }
+    }
+    push_words(&mut result, &s[offset..]);
+    result
+}
+
+/// Segments the input text. With `autoscript` set, words are looked up in
+/// the script the text appears to be written in rather than Simplified first.
+pub fn make_words(s: &str, autoscript: bool, levels: LevelSystem) -> (Vec<Segment>, Stats) {
     static REGEX: Lazy<Regex> = Lazy::new(|| {
-        Regex::new(r"(?s)([\p{Han}]+)|([^\p{Han}]+)")
-            .expect("Internal error: Could not compile regex")
+        Regex::new(concat!(
+            r"(?s)([0-9０-９]+(?:,[0-9]{3})*(?:[.．][0-9０-９]+)?(?:[:：][0-9０-９]{2})?[%％年月日号號点點岁歲]?)",
+            r"|(\p{Han}+)",
+            r"|(\n)",
+            r"|([^\S\n]+)",
+            r"|([\p{Latin}\p{M}]+(?:['’][\p{Latin}\p{M}]+)*)",
+            r"|([\p{P}\p{S}]+)",
+            r"|(.)",
+        ))
+        .expect("Internal error: Could not compile regex")
     });
     let mut stats = Stats::new();
+    let mut segments = Vec::new();
+    stats.script = ScriptCounts::detect(s);
+    let traditional = autoscript && stats.script.script() == Script::Traditional;
+    // Start of a number suffix that turned out to be part of the following word.
+    let mut carry = None;
 
-    (
-        REGEX
-            .captures_iter(s)
-            .flat_map(|chunk| {
-                if let Some(ch) = chunk.get(1) {
-                    cd::tokenize(ch.as_str())
-                        .into_iter()
-                        .map(|chword| {
-                            let mut qr = cd::query_by_simplified(chword);
-                            if qr.is_empty() && cd::is_traditional(chword) {
-                                qr = cd::query_by_traditional(chword);
-                            }
-                            if qr.is_empty() {
-                                return Segment::Plain(chword.to_owned());
-                            }
-                            sort_defs(&mut qr);
-                            if !qr.is_empty() {
-                                let w = &qr[0];
-                                stats.update(&w.simplified, w.hsk);
-                            }
-                            Segment::Chinese(qr)
-                        })
-                        .collect::<Vec<_>>()
+    for chunk in REGEX.captures_iter(s) {
+        let (idx, m) = match chunk
+            .iter()
+            .enumerate()
+            .skip(1)
+            .find_map(|(idx, m)| Some((idx, m?)))
+        {
+            Some(found) => found,
+            None => continue,
+        };
+        let txt = match carry.take() {
+            Some(start) if idx == 2 => &s[start..m.end()],
+            Some(start) => {
+                segments.extend(make_han_chunk(&s[start..m.start()], traditional));
+                m.as_str()
+            }
+            None => m.as_str(),
+        };
+        let newsegs = match idx {
+            1 => {
+                let mut txt = txt;
+                if let Some(suffix) = txt.chars().last().filter(|c| !c.is_ascii()) {
+                    // Leave the suffix to words like 年级 or 月份.
+                    let next = s[m.end()..].chars().next().filter(|c| !c.is_ascii());
+                    if matches!(next, Some(next)
+                        if !lookup(&format!("{suffix}{next}"), traditional).is_empty())
+                    {
+                        txt = &txt[..txt.len() - suffix.len_utf8()];
+                        carry = Some(m.end() - suffix.len_utf8());
+                    }
+                }
+                let ordinal = matches!(segments.last(),
+                    Some(Segment::Chinese(w)) if w.defs[0].simplified == "第");
+                if ordinal {
+                    segments.pop();
+                }
+                let txt = if ordinal {
+                    format!("第{txt}")
                 } else {
-                    chunk
-                        .get(2)
-                        .map(|rmatch| rmatch.as_str())
-                        .unwrap_or_default()
-                        .split_inclusive('\n')
-                        .flat_map(|pchunk| {
-                            let pchunk = pchunk.to_owned();
-                            if pchunk.ends_with('\n') {
-                                vec![Segment::Plain(pchunk), Segment::Break]
-                            } else {
-                                vec![Segment::Plain(pchunk)]
-                            }
-                        })
-                        .collect::<Vec<_>>()
+                    txt.to_owned()
+                };
+                vec![Segment::Number(Number::from_arabic(&txt, false))]
+            }
+            2 => make_han_chunk(txt, traditional),
+            3 => vec![Segment::Break],
+            4 => vec![Segment::Whitespace(txt.to_owned())],
+            5 => vec![Segment::Latin(txt.to_owned())],
+            6 => vec![Segment::Punctuation(txt.to_owned())],
+            _ => {
+                if let Some(Segment::Plain(prev)) = segments.last_mut() {
+                    prev.push_str(txt);
+                    continue;
                 }
+                vec![Segment::Plain(txt.to_owned())]
+            }
+        };
+        // A lone 2 before a measure word is read as 两.
+        if let (Some(Segment::Number(num)), Some(Segment::Chinese(w))) =
+            (segments.last_mut(), newsegs.first())
+        {
+            if matches!(num.text.as_str(), "2" | "２") && is_classifier(&w.defs) {
+                *num = Number::from_arabic(&num.text, true);
+            }
+        }
+        segments.extend(newsegs);
+    }
+    if let Some(start) = carry {
+        segments.extend(make_han_chunk(&s[start..], traditional));
+    }
+    pos::tag(&mut segments);
+    segments
+        .iter()
+        .for_each(|seg| stats.update_segment(seg, levels));
+    (segments, stats)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_segment_kinds() {
+        let (segs, stats) = make_words("我有3.5个apple，\n好 吗?", false, LevelSystem::Hsk2);
+        assert!(matches!(segs[0], Segment::Chinese(_)));
+        assert_eq!(segs[2].as_text(), Some("3.5"));
+        assert_eq!(segs[4], Segment::Latin("apple".to_string()));
+        assert_eq!(segs[5], Segment::Punctuation("，".to_string()));
+        assert_eq!(segs[6], Segment::Break);
+        assert_eq!(segs[8], Segment::Whitespace(" ".to_string()));
+        assert_eq!(segs[10], Segment::Punctuation("?".to_string()));
+        assert_eq!(stats.numbers, 1);
+        assert_eq!(stats.latin, 1);
+        assert_eq!(stats.punctuation, 2);
+    }
+
+    #[test]
+    pub fn test_split_unknown() {
+        let segs = split_unknown("今天天气", false);
+        assert_eq!(segs.len(), 2);
+        assert!(segs.iter().all(|seg| matches!(
+            seg,
+            Segment::Chinese(Word {
+                uncertain: true,
+                ..
             })
-            .collect::<Vec<_>>(),
-        stats,
-    )
+        )));
+        assert_eq!(segs[1].as_chinese().unwrap()[0].simplified, "天气");
+    }
+
+    #[test]
+    pub fn test_numbers() {
+        let hanzi = |s: &str| Number::from_arabic(s, false).hanzi;
+        assert_eq!(hanzi("123"), "一百二十三");
+        assert_eq!(hanzi("1005"), "一千零五");
+        assert_eq!(hanzi("100010"), "十万零一十");
+        assert_eq!(hanzi("2000"), "两千");
+        assert_eq!(hanzi("2022年"), "二〇二二年");
+        assert_eq!(hanzi("3.5%"), "百分之三点五");
+        assert_eq!(hanzi("8:30"), "八点三十");
+        assert_eq!(hanzi("2:05"), "两点零五");
+        assert_eq!(hanzi("第2"), "第二");
+        assert_eq!(Number::from_arabic("2", true).hanzi, "两");
+
+        let aligned = |s: &str| Number::from_arabic(s, false).aligned;
+        assert!(aligned("2022年"));
+        assert!(!aligned("1200"));
+        assert!(!aligned("1001"));
+        assert!(!aligned("8:30"));
+        assert!(!aligned("12"));
+        assert!(Number::from_hanzi("一千二百").unwrap().aligned);
+
+        let value = |s: &str| Number::from_hanzi(s).and_then(|n| n.value);
+        assert_eq!(value("一百二十三"), Some("123".to_string()));
+        assert_eq!(value("三万零五"), Some("30005".to_string()));
+        assert_eq!(value("第十"), Some("10th".to_string()));
+        assert_eq!(value("三点五"), Some("3.5".to_string()));
+        assert_eq!(value("二〇二二"), Some("2022".to_string()));
+        assert_eq!(value("三四"), None);
+        assert_eq!(value("万一"), None);
+        // Too long for a u64 rather than overflowing.
+        assert_eq!(value(&"九".repeat(25)), None);
+
+        let tones = |s: &str| {
+            Number::from_hanzi(s)
+                .unwrap()
+                .readings
+                .iter()
+                .map(|r| r.tone)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tones("一百"), vec![4, 3]);
+        assert_eq!(tones("一万"), vec![2, 4]);
+    }
+
+    #[test]
+    pub fn test_number_segments() {
+        let (segs, _) = make_words("第10章有2只猫, 2年级", false, LevelSystem::Hsk2);
+        assert_eq!(segs[0].as_text(), Some("第10"));
+        assert!(matches!(&segs[3], Segment::Number(n) if n.hanzi == "两"));
+        assert!(matches!(&segs[8], Segment::Number(n) if n.text == "2"));
+        assert_eq!(segs[9].as_chinese().unwrap()[0].simplified, "年级");
+        for text in ["一点一点地学", "一点一滴", "差一点点"] {
+            let (segs, _) = make_words(text, false, LevelSystem::Hsk2);
+            assert!(
+                !segs.iter().any(|seg| matches!(seg, Segment::Number(_))),
+                "{text} has a number"
+            );
+        }
+        let (segs, _) = make_words("一点五倍", false, LevelSystem::Hsk2);
+        assert!(matches!(&segs[0], Segment::Number(n) if n.value.as_deref() == Some("1.5")));
+    }
+
+    #[test]
+    pub fn test_idioms() {
+        let (segs, _) = make_words("他一心一意地画蛇添足", false, LevelSystem::Hsk2);
+        let idioms = segs
+            .iter()
+            .filter_map(|seg| match seg {
+                Segment::Chinese(w) if w.idiom => Some(w.defs[0].simplified.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(idioms, vec!["一心一意", "画蛇添足"]);
+        let (segs, _) = make_words("百闻不如一见", false, LevelSystem::Hsk2);
+        assert!(matches!(&segs[..], [Segment::Chinese(w)] if w.idiom));
+        assert!(literal_gloss("画蛇").starts_with("画 to draw · 蛇 snake"));
+    }
+
+    #[test]
+    pub fn test_idiom_list() {
+        include_str!("../assets/chengyu.txt")
+            .lines()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .for_each(|l| assert!(is_idiom(&lookup(l, false)), "{l} isn't an idiom"));
+    }
+
+    #[test]
+    pub fn test_interlinear_gloss() {
+        let defs = lookup("学习", false);
+        assert_eq!(interlinear_gloss(&defs, 0).as_deref(), Some("to learn"));
+        assert_eq!(interlinear_gloss(&defs, 6).as_deref(), Some("to…"));
+        assert_eq!(interlinear_gloss(&defs, 3).as_deref(), Some("to…"));
+        assert_eq!(interlinear_gloss(&defs, 2).as_deref(), Some("t…"));
+        for max_len in 1..10 {
+            let gloss = interlinear_gloss(&defs, max_len).unwrap();
+            assert!(gloss.chars().count() <= max_len, "{gloss} is too long");
+        }
+        assert_eq!(interlinear_gloss(&[], 10), None);
+    }
+
+    #[test]
+    pub fn test_word_hint() {
+        let word = Word::new(lookup("学习", false));
+        assert_eq!(
+            word_hint(Hint::OtherScript, &word, "1", true, 0).as_deref(),
+            Some("學習")
+        );
+        assert_eq!(
+            word_hint(Hint::OtherScript, &word, "1", false, 0).as_deref(),
+            Some("学习")
+        );
+        assert_eq!(
+            word_hint(Hint::Hsk, &word, "1", true, 0).as_deref(),
+            Some("1")
+        );
+        assert_eq!(word_hint(Hint::Pinyin, &word, "1", true, 0), None);
+        assert_eq!(
+            word_hint(Hint::Frequency, &word, "1", true, 0).as_deref(),
+            Some("#583")
+        );
+        assert_eq!(
+            word_hint(
+                Hint::Frequency,
+                &Word::new(lookup("画蛇添足", false)),
+                "",
+                true,
+                0
+            ),
+            None
+        );
+        let word = Word::new(lookup("你", false));
+        assert_eq!(word_hint(Hint::OtherScript, &word, "1", true, 0), None);
+    }
 }
@@ -0,0 +1,116 @@
+#!/usr/bin/env python3
+"""Generates assets/characters.txt from the Unicode Unihan database and an
+Ideographic Description Sequence file.
+
+Usage: tools/characters.py UNIHAN_DIR CJKRadicals.txt ids.txt > assets/characters.txt
+
+- UNIHAN_DIR is the unzipped https://www.unicode.org/Public/UCD/latest/ucd/Unihan.zip,
+  kRSUnicode and kTotalStrokes are read from whichever file has them.
+- CJKRadicals.txt is https://www.unicode.org/Public/UCD/latest/ucd/CJKRadicals.txt.
+- ids.txt is https://github.com/cjkvi/cjkvi-ids/blob/master/ids.txt, lines
+  like "U+597D<tab>好<tab>⿰女子". It's derived from the CHISE IDS database,
+  which is GPL v2 licensed, so its license has to be checked before the
+  output is shipped.
+
+Only characters with a Mandarin reading are written.
+"""
+
+import glob
+import os
+import re
+import sys
+
+# Forms radicals take as a component, by Kangxi radical number, in the order
+# they're looked for in the IDS. A "'" marks the simplified form.
+RADICAL_FORMS = {
+    "9": "亻人", "18": "刂刀", "26": "卩㔾", "47": "巛川", "50": "巾",
+    "58": "彐", "61": "忄㣺心", "64": "扌手", "66": "攵攴", "74": "月",
+    "78": "歹", "85": "氵氺水", "86": "灬火", "93": "牜牛", "94": "犭犬",
+    "96": "王玉", "113": "礻示", "122": "罒网", "125": "耂老", "130": "月肉",
+    "140": "艹", "145": "衤衣", "149": "言", "149'": "讠", "120": "糹糸",
+    "120'": "纟", "146": "覀西", "157": "⻊足", "162": "辶", "163": "阝邑",
+    "167": "釒金", "167'": "钅", "169": "門", "169'": "门", "170": "阝阜",
+    "184": "飠食", "184'": "饣", "187": "馬", "187'": "马", "196": "鳥",
+    "196'": "鸟",
+}
+
+IDC = re.compile(r"[⿰-⿿]")
+
+
+def unihan(path):
+    """kRSUnicode, kTotalStrokes and kMandarin by character."""
+    result = {}
+    for name in glob.glob(os.path.join(path, "Unihan*.txt")):
+        with open(name, encoding="utf-8") as f:
+            for line in f:
+                if line.startswith("#") or not line.strip():
+                    continue
+                code, field, value = line.rstrip("\n").split("\t", 2)
+                if field in ("kRSUnicode", "kTotalStrokes", "kMandarin"):
+                    result.setdefault(chr(int(code[2:], 16)), {})[field] = value
+    return result
+
+
+def radicals(path):
+    """The unified ideograph of each radical number, like "120'" -> 纟."""
+    result = {}
+    with open(path, encoding="utf-8") as f:
+        for line in f:
+            line = line.split("#", 1)[0].strip()
+            if not line:
+                continue
+            number, _, ideograph = [field.strip() for field in line.split(";")]
+            result[number] = chr(int(ideograph, 16))
+    return result
+
+
+def ids(path):
+    """The first IDS of each character, without source tags like [GTJ]."""
+    result = {}
+    with open(path, encoding="utf-8") as f:
+        for line in f:
+            if line.startswith(("#", ";")) or not line.strip():
+                continue
+            fields = line.rstrip("\n").split("\t")
+            if len(fields) >= 3:
+                result[fields[1]] = re.sub(r"\[[^]]*\]", "", fields[2])
+    return result
+
+
+def main(unihan_dir, radicals_path, ids_path):
+    data = unihan(unihan_dir)
+    forms = radicals(radicals_path)
+    sequences = ids(ids_path)
+    print("# Characters with their radical as written in the character, Kangxi radical")
+    print("# number, total strokes and Ideographic Description Sequence, tab separated.")
+    print("# Generated by tools/characters.py from the Unicode Unihan database")
+    print("# (kRSUnicode, kTotalStrokes), Copyright © Unicode, Inc., under the Unicode")
+    print("# License v3 (https://www.unicode.org/license.txt), and the IDS of cjkvi-ids")
+    print("# (https://github.com/cjkvi/cjkvi-ids), derived from CHISE IDS, GPL v2.")
+    print("# Characters that don't split further are their own IDS.")
+    for c in sorted(data):
+        fields = data[c]
+        if "kMandarin" not in fields or "kRSUnicode" not in fields:
+            continue
+        if "kTotalStrokes" not in fields:
+            continue
+        # The first value is the Chinese one.
+        number = fields["kRSUnicode"].split()[0].split(".")[0]
+        strokes = fields["kTotalStrokes"].split()[0]
+        seq = sequences.get(c, c)
+        # Sequences with unencoded components are left as the character.
+        if not IDC.match(seq) or any(0xE000 <= ord(comp) <= 0xF8FF or comp == "？" for comp in seq):
+            seq = c
+        radical = next(
+            (form for form in RADICAL_FORMS.get(number, "") if form in seq),
+            forms.get(number),
+        )
+        if radical is None:
+            continue
+        print(f"{c}\t{radical}\t{number.rstrip(chr(39))}\t{strokes}\t{seq}")
+
+
+if __name__ == "__main__":
+    if len(sys.argv) != 4:
+        sys.exit(__doc__)
+    main(*sys.argv[1:])
@@ -0,0 +1,46 @@
+#!/usr/bin/env python3
+"""Generates assets/frequency.txt from the word frequencies of jieba.
+
+Usage: tools/frequency.py dict.txt [COUNT] > assets/frequency.txt
+
+dict.txt is the dictionary of jieba (https://github.com/fxsjy/jieba), also
+bundled with jieba-rs as src/data/dict.txt, with lines like "的 318825 uj".
+Both are MIT licensed. The COUNT most frequent words, 20000 by default, are
+written one per line, most frequent first.
+"""
+
+import sys
+
+
+def is_han(c):
+    return (
+        "㐀" <= c <= "鿿"
+        or "\U00020000" <= c <= "\U0003134f"
+        or "豈" <= c <= "﫿"
+    )
+
+
+def main(path, count=20000):
+    words = {}
+    with open(path, encoding="utf-8") as f:
+        for line in f:
+            fields = line.split()
+            if len(fields) < 2 or not all(is_han(c) for c in fields[0]):
+                continue
+            words[fields[0]] = max(words.get(fields[0], 0), int(fields[1]))
+    # Ties keep the dictionary's order.
+    ranked = sorted(words, key=lambda word: -words[word])[: int(count)]
+    print("# The most frequent words, most frequent first, one per line.")
+    print("# Generated by tools/frequency.py from the dictionary of jieba")
+    print("# (https://github.com/fxsjy/jieba), Copyright (c) 2013 Sun Junyi,")
+    print("# as bundled with jieba-rs (https://github.com/messense/jieba-rs),")
+    print("# Copyright (c) 2018 - 2019 messense, Copyright (c) 2019 Paul Meng,")
+    print("# both under the MIT License.")
+    for word in ranked:
+        print(word)
+
+
+if __name__ == "__main__":
+    if len(sys.argv) not in (2, 3):
+        sys.exit(__doc__)
+    main(*sys.argv[1:])